@@ -0,0 +1,180 @@
+//! the cost of the render path on an offscreen state, so it runs without an X server,
+//! a GPU (or a software one like lavapipe) is still needed
+//!
+//! run with `cargo bench -p mdry`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use glyphon::Metrics;
+use mdry::{
+    color::Color,
+    shapes::{Rect, Shape},
+    State,
+};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 35;
+const FONT_SIZE: f32 = 16.;
+
+fn offscreen_state() -> State<'static> {
+    pollster::block_on(State::new_offscreen(
+        WIDTH,
+        HEIGHT,
+        mdry::wgpu::TextureFormat::Bgra8Unorm,
+    ))
+}
+
+/// the `i`th of a row of small rects, moved by `shift` so consecutive frames differ
+fn rect(i: usize, shift: f32) -> Shape {
+    Shape::Rect(Rect {
+        x: (i % 480) as f32 * 4. + shift,
+        y: (i / 480) as f32 * 4.,
+        width: 3,
+        height: 3,
+        color: Color::rgb(233, 86, 120),
+    })
+}
+
+fn rects(c: &mut Criterion) {
+    let mut state = offscreen_state();
+    let mut group = c.benchmark_group("rects");
+
+    for count in [10, 100, 1000] {
+        group.bench_with_input(BenchmarkId::new("mesh", count), &count, |b, &count| {
+            b.iter(|| {
+                (0..count)
+                    .map(|i| State::create_mesh(rect(i, 0.)))
+                    .collect::<Vec<_>>()
+            })
+        });
+
+        // meshes that didn't change since the last frame aren't uploaded again,
+        // so every frame moves them
+        let mut frame = 0;
+        group.bench_with_input(BenchmarkId::new("upload", count), &count, |b, &count| {
+            b.iter(|| {
+                frame += 1;
+                for i in 0..count {
+                    state.draw_shape_absolute(rect(i, (frame % 2) as f32));
+                }
+                state.update().unwrap();
+                state.clear_shapes();
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("render", count), &count, |b, &count| {
+            b.iter(|| {
+                frame += 1;
+                state.clear_background(Color::rgb(26, 29, 36));
+                for i in 0..count {
+                    state.draw_shape_absolute(rect(i, (frame % 2) as f32));
+                }
+                state.update().unwrap();
+                state.render().unwrap();
+                state.clear_shapes();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn text(c: &mut Criterion) {
+    let mut state = offscreen_state();
+    let metrics = Metrics::new(FONT_SIZE, FONT_SIZE);
+    let mut group = c.benchmark_group("text");
+
+    for length in [8, 64, 256] {
+        let content: String = "shareet ".chars().cycle().take(length).collect();
+
+        group.bench_with_input(
+            BenchmarkId::new("measure", length),
+            &content,
+            |b, content| b.iter(|| black_box(state.measure_text(content, metrics))),
+        );
+
+        // the shaped buffer is cached after the first frame
+        group.bench_with_input(BenchmarkId::new("draw", length), &content, |b, content| {
+            b.iter(|| {
+                state.clear_background(Color::rgb(26, 29, 36));
+                state.draw_text_absolute_cached(
+                    content,
+                    0.,
+                    0.,
+                    Color::rgb(191, 189, 182),
+                    FONT_SIZE,
+                );
+                state.update().unwrap();
+                state.render().unwrap();
+            })
+        });
+
+        // a new text every frame, like a clock, so it's shaped every time
+        let mut frame = 0u64;
+        group.bench_with_input(
+            BenchmarkId::new("draw_changing", length),
+            &content,
+            |b, content| {
+                b.iter(|| {
+                    frame += 1;
+                    let content = format!("{content}{frame}");
+                    state.clear_background(Color::rgb(26, 29, 36));
+                    state.draw_text_absolute_cached(
+                        &content,
+                        0.,
+                        0.,
+                        Color::rgb(191, 189, 182),
+                        FONT_SIZE,
+                    );
+                    state.update().unwrap();
+                    state.render().unwrap();
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// a clock changing every frame, with [`State::update_text`] replacing the line
+/// in place against building the text again
+fn owned_text(c: &mut Criterion) {
+    let mut state = offscreen_state();
+    let mut group = c.benchmark_group("owned_text");
+    let clock = |frame: u64| {
+        format!(
+            "{:02}:{:02}:{:02}",
+            frame / 3600 % 24,
+            frame / 60 % 60,
+            frame % 60
+        )
+    };
+
+    let handle = state.create_text(&clock(0), FONT_SIZE, Color::rgb(191, 189, 182));
+    let mut frame = 0;
+    group.bench_function("update", |b| {
+        b.iter(|| {
+            frame += 1;
+            state.update_text(handle, &clock(frame));
+            state.draw_text(handle, 0., 0.);
+            state.update().unwrap();
+        })
+    });
+    state.remove_text(handle);
+
+    let mut handle = state.create_text(&clock(0), FONT_SIZE, Color::rgb(191, 189, 182));
+    group.bench_function("recreate", |b| {
+        b.iter(|| {
+            frame += 1;
+            state.remove_text(handle);
+            handle = state.create_text(&clock(frame), FONT_SIZE, Color::rgb(191, 189, 182));
+            state.draw_text(handle, 0., 0.);
+            state.update().unwrap();
+        })
+    });
+    state.remove_text(handle);
+
+    group.finish();
+}
+
+criterion_group!(benches, rects, text, owned_text);
+criterion_main!(benches);
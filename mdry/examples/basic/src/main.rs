@@ -5,11 +5,9 @@ use mdry::{
     shapes::{Rect, Shape},
     window::{Atoms, Window, WindowType},
     x11rb::{
-        self,
         connection::Connection,
         protocol::xproto::{
-            AtomEnum, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, EventMask, PropMode,
-            WindowClass,
+            AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, WindowClass,
         },
         wrapper::ConnectionExt as _,
         xcb_ffi::XCBConnection,
@@ -52,29 +50,17 @@ fn main() -> Result<(), Error> {
         let event = connection.wait_for_event()?;
         let mut event_option = Some(event);
         while let Some(event) = event_option {
+            if state.input(&event) {
+                redraw = true;
+            }
+
             match event {
                 Event::ClientMessage(event) => {
                     // window manager requested to close the window
-                    if event.data.as_data32()[0] == state.window.atoms.WM_DELETE_WINDOW {
+                    if event.data.as_data32()[0] == state.window().atoms.WM_DELETE_WINDOW {
                         return Ok(());
                     }
                 }
-                Event::Expose(event) => {
-                    let width = event.width as u32;
-                    let height = event.height as u32;
-                    let configure = ConfigureWindowAux::new().width(width).height(height);
-                    connection.configure_window(event.window, &configure)?;
-                    state.resize(width, height);
-
-                    redraw = true;
-                }
-                Event::ConfigureNotify(event) => {
-                    let width = event.width as u32;
-                    let height = event.height as u32;
-                    state.resize(width, height);
-
-                    redraw = true;
-                }
                 _ => {}
             }
 
@@ -84,7 +70,7 @@ fn main() -> Result<(), Error> {
 }
 
 async fn create_state<'a>(window: Window<'a>) -> State {
-    State::new(window).await
+    State::new(window, 4, mdry::wgpu::PresentMode::Fifo).await
 }
 
 pub fn create_window(
@@ -129,36 +115,6 @@ pub fn create_window(
         &create,
     )?;
 
-    connection
-        .change_property8(
-            PropMode::REPLACE,
-            window_id,
-            atoms._NET_WM_NAME,
-            AtomEnum::STRING,
-            b"simple",
-        )?
-        .check()?;
-
-    connection
-        .change_property8(
-            PropMode::REPLACE,
-            window_id,
-            atoms.WM_NAME,
-            AtomEnum::STRING,
-            b"simple",
-        )?
-        .check()?;
-
-    connection
-        .change_property8(
-            PropMode::REPLACE,
-            window_id,
-            x11rb::protocol::xproto::Atom::from(x11rb::protocol::xproto::AtomEnum::WM_CLASS),
-            AtomEnum::STRING,
-            b"simple",
-        )?
-        .check()?;
-
     connection
         .change_property32(
             PropMode::REPLACE,
@@ -179,11 +135,7 @@ pub fn create_window(
         )?
         .check()?;
 
-    connection.map_window(window_id)?;
-
-    connection.flush()?;
-
-    Ok(Window {
+    let window = Window {
         xid: window_id,
         connection,
         screen_num,
@@ -194,5 +146,18 @@ pub fn create_window(
         atoms,
         display_scale,
         window_type: WindowType::Normal,
-    })
+        transparent: false,
+        cursors: Default::default(),
+        atom_cache: Default::default(),
+    };
+
+    window.set_title("simple")?;
+    // shows up as `WM_CLASS(STRING) = "simple", "Simple"` in xprop
+    window.set_class("simple", "Simple")?;
+
+    connection.map_window(window_id)?;
+
+    connection.flush()?;
+
+    Ok(window)
 }
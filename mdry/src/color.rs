@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Color {
     r: u8,
     g: u8,
@@ -7,9 +7,10 @@ pub struct Color {
 }
 
 impl Color {
+    /// parses `#rrggbb`, or `#rrggbbaa` for a color with alpha
     pub fn hex(hex: &str) -> Option<Self> {
         if let Some(hex) = hex.strip_prefix("#") {
-            if hex.len() != 6 {
+            if hex.len() != 6 && hex.len() != 8 {
                 return None;
             }
 
@@ -26,7 +27,7 @@ impl Color {
                 r: hex[0],
                 g: hex[1],
                 b: hex[2],
-                a: 255,
+                a: hex.get(3).copied().unwrap_or(255),
             });
         }
 
@@ -50,6 +51,27 @@ impl Color {
         ]
     }
 
+    /// the color with every channel multiplied by its alpha, `[r * a, g * a, b * a, a]`,
+    /// for a surface composited with [`wgpu::CompositeAlphaMode::PreMultiplied`]
+    pub fn rgba_premultiplied_f32(&self) -> [f32; 4] {
+        let [r, g, b, a] = self.rgba_f32();
+        [r * a, g * a, b * a, a]
+    }
+
+    /// the color decoded from sRGB to linear light, with alpha left as is,
+    /// for targets with an `*Srgb` format which encode what's written to them
+    pub fn rgba_linear_f32(&self) -> [f32; 4] {
+        let linear = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let [r, g, b, a] = self.rgba_f32();
+        [linear(r), linear(g), linear(b), a]
+    }
+
     pub fn rgba_f64(&self) -> [f64; 4] {
         [
             self.r as f64 / 255.,
@@ -74,6 +96,123 @@ impl Color {
     pub fn to_argb_u32(&self) -> u32 {
         ((self.a as u32) << 24) | ((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32
     }
+
+    /// linearly interpolates every channel (including alpha) from `self` to `other`
+    ///
+    /// `t` is clamped to `0.0..=1.0`, 0 gives `self` and 1 gives `other`
+    pub fn mix(&self, other: &Color, t: f32) -> Color {
+        let t = t.clamp(0., 1.);
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+
+        Self {
+            r: lerp(self.r, other.r),
+            g: lerp(self.g, other.g),
+            b: lerp(self.b, other.b),
+            a: lerp(self.a, other.a),
+        }
+    }
+
+    pub fn with_alpha(&self, a: u8) -> Color {
+        Self { a, ..*self }
+    }
+
+    /// moves the lightness `amount` of the way towards white,
+    /// `lighten(0.)` is the same color and `lighten(1.)` is white
+    pub fn lighten(&self, amount: f32) -> Color {
+        let (h, s, l) = self.hsl();
+        let l = l + (1. - l) * amount.clamp(0., 1.);
+        Self::from_hsl(h, s, l, self.a)
+    }
+
+    /// moves the lightness `amount` of the way towards black,
+    /// `darken(0.)` is the same color and `darken(1.)` is black
+    pub fn darken(&self, amount: f32) -> Color {
+        let (h, s, l) = self.hsl();
+        let l = l * (1. - amount.clamp(0., 1.));
+        Self::from_hsl(h, s, l, self.a)
+    }
+
+    /// hue in degrees, saturation and lightness in `0.0..=1.0`
+    fn hsl(&self) -> (f32, f32, f32) {
+        let [r, g, b] = self.rgb_f32();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.;
+
+        if max == min {
+            return (0., 0., l);
+        }
+
+        let d = max - min;
+        let s = if l > 0.5 {
+            d / (2. - max - min)
+        } else {
+            d / (max + min)
+        };
+
+        let h = if max == r {
+            (g - b) / d + if g < b { 6. } else { 0. }
+        } else if max == g {
+            (b - r) / d + 2.
+        } else {
+            (r - g) / d + 4.
+        };
+
+        (h * 60., s, l)
+    }
+
+    fn from_hsl(h: f32, s: f32, l: f32, a: u8) -> Self {
+        let c = (1. - (2. * l - 1.).abs()) * s;
+        let x = c * (1. - ((h / 60.) % 2. - 1.).abs());
+        let m = l - c / 2.;
+
+        let (r, g, b) = match h {
+            h if h < 60. => (c, x, 0.),
+            h if h < 120. => (x, c, 0.),
+            h if h < 180. => (0., c, x),
+            h if h < 240. => (0., x, c),
+            h if h < 300. => (x, 0., c),
+            _ => (c, 0., x),
+        };
+
+        let channel = |v: f32| ((v + m).clamp(0., 1.) * 255.).round() as u8;
+
+        Self {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+            a,
+        }
+    }
+}
+
+/// `#rrggbb`, or `#rrggbbaa` if the color isn't opaque, which [`Color::hex`] parses back
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a < 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Color::hex(&value).ok_or_else(|| {
+            serde::de::Error::custom(format!("`{value}` is not a #rrggbb or #rrggbbaa color"))
+        })
+    }
 }
 
 impl Into<wgpu::Color> for Color {
@@ -93,3 +232,70 @@ impl Into<glyphon::Color> for Color {
         glyphon::Color::rgba(self.r, self.g, self.b, self.a)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_and_premultiplied() {
+        let half_red = Color::rgba(255, 0, 0, 128);
+        let a = 128. / 255.;
+
+        assert_eq!(half_red.rgba_f32(), [1., 0., 0., a]);
+        assert_eq!(half_red.rgba_premultiplied_f32(), [a, 0., 0., a]);
+        // opaque colors are the same either way
+        assert_eq!(
+            Color::rgb(255, 0, 0).rgba_premultiplied_f32(),
+            Color::rgb(255, 0, 0).rgba_f32()
+        );
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for hex in ["#1a1d24", "#bfbdb6", "#e9567880", "#00000000"] {
+            let color = Color::hex(hex).unwrap();
+            assert_eq!(color.to_string(), hex);
+            assert_eq!(Color::hex(&color.to_string()), Some(color));
+        }
+
+        // opaque colors are displayed without their alpha
+        assert_eq!(Color::hex("#1a1d24ff").unwrap().to_string(), "#1a1d24");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use serde::Deserialize;
+
+        for color in [Color::rgb(26, 29, 36), Color::rgba(233, 86, 120, 128)] {
+            let value = toml::Value::try_from(color).unwrap();
+            assert_eq!(value, toml::Value::String(color.to_string()));
+            assert_eq!(Color::deserialize(value).unwrap(), color);
+        }
+
+        assert!(Color::deserialize(toml::Value::String("red".into())).is_err());
+    }
+
+    #[test]
+    fn mix_halfway() {
+        let black = Color::rgb(0, 0, 0);
+        let white = Color::rgb(255, 255, 255);
+
+        assert_eq!(black.mix(&white, 0.5), Color::rgb(128, 128, 128));
+        // the same either way around
+        assert_eq!(white.mix(&black, 0.5), Color::rgb(128, 128, 128));
+    }
+
+    #[test]
+    fn mix_endpoints() {
+        let black = Color::rgba(0, 0, 0, 0);
+        let white = Color::rgb(255, 255, 255);
+
+        assert_eq!(black.mix(&white, 0.), black);
+        assert_eq!(black.mix(&white, 1.), white);
+        // out of range is clamped
+        assert_eq!(black.mix(&white, -1.), black);
+        assert_eq!(black.mix(&white, 2.), white);
+    }
+}
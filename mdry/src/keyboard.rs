@@ -0,0 +1,94 @@
+use x11rb::{
+    connection::Connection,
+    errors::ReplyError,
+    protocol::xproto::{ConnectionExt, KeyButMask, Keycode, Keysym, ModMask},
+};
+
+const XK_NUM_LOCK: Keysym = 0xff7f;
+
+/// Translates keycodes to keysyms using the server's keyboard mapping
+///
+/// this only handles the shift level, which is enough for plain text input
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    min_keycode: Keycode,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<Keysym>,
+}
+
+impl Keymap {
+    pub fn new(connection: &impl Connection) -> Result<Self, ReplyError> {
+        let setup = connection.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+
+        let mapping = connection
+            .get_keyboard_mapping(min_keycode, count)?
+            .reply()?;
+
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: mapping.keysyms_per_keycode,
+            keysyms: mapping.keysyms,
+        })
+    }
+
+    /// returns `None` for keycodes that don't have a keysym
+    pub fn keysym(&self, keycode: Keycode, modifiers: KeyButMask) -> Option<Keysym> {
+        let index = keycode.checked_sub(self.min_keycode)? as usize;
+        let per_keycode = self.keysyms_per_keycode as usize;
+        let syms = self
+            .keysyms
+            .get(index * per_keycode..(index + 1) * per_keycode)?;
+
+        let shifted = modifiers.contains(KeyButMask::SHIFT) ^ modifiers.contains(KeyButMask::LOCK);
+
+        // a keycode with only a lowercase keysym uses it for both levels
+        let keysym = match syms {
+            [lower, 0, ..] | [lower] => *lower,
+            [lower, upper, ..] => {
+                if shifted {
+                    *upper
+                } else {
+                    *lower
+                }
+            }
+            [] => 0,
+        };
+
+        (keysym != 0).then_some(keysym)
+    }
+
+    /// every keycode that has `keysym` on any of its levels
+    pub fn keycodes(&self, keysym: Keysym) -> Vec<Keycode> {
+        let per_keycode = self.keysyms_per_keycode as usize;
+        if per_keycode == 0 {
+            return Vec::new();
+        }
+
+        self.keysyms
+            .chunks(per_keycode)
+            .enumerate()
+            .filter(|(_, syms)| syms.contains(&keysym))
+            .map(|(i, _)| self.min_keycode + i as u8)
+            .collect()
+    }
+
+    /// the modifier Num Lock is mapped to, usually Mod2, or an empty mask if it isn't mapped
+    pub fn num_lock_mask(&self, connection: &impl Connection) -> Result<ModMask, ReplyError> {
+        let mapping = connection.get_modifier_mapping()?.reply()?;
+        // 8 modifiers, from Shift to Mod5, with the same number of keycodes each
+        let per_modifier = mapping.keycodes.len() / 8;
+        if per_modifier == 0 {
+            return Ok(ModMask::from(0u16));
+        }
+
+        let num_lock = self.keycodes(XK_NUM_LOCK);
+        let modifier = mapping
+            .keycodes
+            .chunks(per_modifier)
+            .position(|keycodes| keycodes.iter().any(|keycode| num_lock.contains(keycode)));
+
+        Ok(ModMask::from(modifier.map_or(0, |i| 1u16 << i)))
+    }
+}
@@ -1,33 +1,61 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use glyphon::{Attrs, FontSystem, Metrics, Shaping, SwashCache, TextArea, TextAtlas};
+use painter::Painter;
 use renderer::{
-    measure_text, CachedText, Font, ManagedText, Renderer, TextCacheKey, TextRenderer, TextTypes,
+    fit_to_text, measure_text, CachedText, Font, FontStack, GpuTexture, ManagedText, Renderer,
+    TextCacheKey, TextDecoration, TextHandle, TextRenderer, TextTypes, TexturedQuad,
 };
-use shapes::{Mesh, Shape};
+use shapes::{ClipRect, Mesh, ProgressBar, Rect, Shape};
 use wgpu::MultisampleState;
 use window::Window;
 
 use crate::renderer::TextInner;
 
+pub use image;
+pub use wgpu;
+
 pub mod x11rb {
     pub use x11rb::protocol::Event;
     pub use x11rb::*;
 }
 
 pub mod color;
+pub mod keyboard;
+pub mod painter;
 pub mod renderer;
 pub mod shapes;
+pub mod snapshot;
 pub mod window;
 
+/// A handle to an icon loaded with [`State::load_icon`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IconHandle(PathBuf);
+
+impl IconHandle {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct VertexColored {
     position: [f32; 3],
     color: [f32; 3],
 }
 
 impl VertexColored {
+    /// moves the vertex by `dx` and `dy` pixels
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.position[0] += dx;
+        self.position[1] += dy;
+    }
+
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<VertexColored>() as wgpu::BufferAddress,
@@ -48,8 +76,54 @@ impl VertexColored {
     }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct VertexTextured {
+    position: [f32; 3],
+    uv: [f32; 2],
+}
+
+impl VertexTextured {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<VertexTextured>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        }
+    }
+}
+
+/// how many frames a cached text buffer is kept without being drawn
+const TEXT_CACHE_MAX_AGE: u64 = 120;
+
+/// What [`State::render`] draws into
+enum RenderTarget {
+    Surface(wgpu::Surface),
+    /// a texture that can be read back with [`State::read_pixels`]
+    Offscreen(wgpu::Texture),
+}
+
+/// where the buffer of a queued text comes from
+#[derive(Debug)]
+enum Allocation {
+    Managed(Option<Arc<TextInner>>),
+    Cached(TextCacheKey),
+    Owned(TextHandle),
+}
+
 pub struct State<'a> {
-    surface: wgpu::Surface,
+    target: RenderTarget,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
@@ -58,21 +132,68 @@ pub struct State<'a> {
     // The window must be declared after the surface so
     // it gets dropped after it as the surface contains
     // unsafe references to the window's resources.
-    pub window: Window<'a>,
+    /// `None` for offscreen states
+    window: Option<Window<'a>>,
     renderer: Renderer,
     text_renderer: TextRenderer,
+    /// the GPU and backend picked by `request_adapter`
+    adapter_info: wgpu::AdapterInfo,
+    /// the present modes supported by the surface
+    present_modes: Vec<wgpu::PresentMode>,
+    sample_count: u32,
+    /// multisampled color target that gets resolved into the surface texture,
+    /// `None` when `sample_count` is 1
+    msaa_view: Option<wgpu::TextureView>,
     clear_background: Option<crate::color::Color>,
+    /// kept until the frame is rendered, to prepare them again if the window is resized in between
     texts: Vec<TextTypes>,
-    meshes: Vec<Mesh>,
+    /// texts created with [`State::create_text`], kept until they're removed
+    owned_texts: HashMap<TextHandle, TextInner>,
+    next_text_handle: u64,
+    /// the size the queued texts were last prepared for
+    prepared_size: (u32, u32),
+    /// the buffer each queued text is drawn with, only kept around for its capacity
+    text_allocations: Vec<Allocation>,
+    /// immediate meshes queued for this frame, their z order and clip rect
+    meshes: Vec<(Mesh, f32, Option<ClipRect>)>,
+    /// the immediate meshes currently in the renderer's vertex/index buffers
+    uploaded_meshes: Vec<(Mesh, f32, Option<ClipRect>)>,
+    /// the part of the window being redrawn this frame, set by [`State::clear_region`]
+    region: Option<ClipRect>,
+    /// the region the retained meshes in the renderer's buffers are clipped to
+    uploaded_region: Option<ClipRect>,
+    /// the current clip rects, each one is already intersected with the one before it
+    clip_stack: Vec<ClipRect>,
+    /// the current translations, each one already includes the ones before it
+    translation_stack: Vec<(f32, f32)>,
+    /// textured quads queued for this frame, drawn over the meshes
+    textured_quads: Vec<TexturedQuad>,
+    /// stretched over the whole window under everything else, see [`State::set_background_texture`]
+    background_texture: Option<Arc<GpuTexture>>,
+    /// decoded icons, uploaded once and kept until they're unloaded
+    icons: HashMap<PathBuf, Arc<GpuTexture>>,
+    /// retained meshes, drawn below the immediate ones
+    painter: Option<Painter>,
     /// kind of a stupid way to measure the text size
     measure_text_buffer: glyphon::Buffer,
-    text_cache: HashMap<TextCacheKey, glyphon::Buffer>,
+    /// shaped buffers of cached texts, and the frame they were last drawn in
+    text_cache: HashMap<TextCacheKey, (glyphon::Buffer, u64)>,
+    /// counts calls to [`State::update`], to find cached texts that aren't drawn anymore
+    frame: u64,
     default_font: Font,
 }
 
 impl<'a> State<'a> {
     // Creating some of the wgpu types requires async code
-    pub async fn new(window: Window<'a>) -> State<'a> {
+    /// `sample_count` is the number of MSAA samples (1, 2, 4 or 8),
+    /// 1x is used instead if the adapter doesn't support the requested count
+    /// `present_mode` is used if the surface supports it, otherwise it falls back
+    /// to [`wgpu::PresentMode::Fifo`] (vsync), which is always supported
+    pub async fn new(
+        window: Window<'a>,
+        sample_count: u32,
+        present_mode: wgpu::PresentMode,
+    ) -> State<'a> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             dx12_shader_compiler: Default::default(),
@@ -96,47 +217,135 @@ impl<'a> State<'a> {
             .await
             .expect("Could not get adapter");
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    // WebGL doesn't support all of wgpu's features, so if
-                    // we're building for the web we'll have to disable some.
-                    limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
-                },
-                None, // Trace path
-            )
-            .await
-            .unwrap();
+        let (device, queue) = request_device(&adapter).await;
 
         let surface_caps = surface.get_capabilities(&adapter);
-        // Shader code in this tutorial assumes an sRGB surface texture. Using a different
-        // one will result all the colors coming out darker. If you want to support non
-        // sRGB surfaces, you'll need to account for that when drawing to the frame.
+        // colors are given in sRGB, a linear `*Unorm` format stores them as is,
+        // the shaders decode them first if only an `*Srgb` format is available
         let surface_format = preferred_framebuffer_format(&surface_caps.formats).unwrap();
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width,
             height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            present_mode: supported_present_mode(&surface_caps.present_modes, present_mode),
+            alpha_mode: preferred_alpha_mode(&surface_caps.alpha_modes, window.transparent),
             view_formats: vec![],
         };
         surface.configure(&device, &config);
 
-        let renderer = Renderer::new(config.format, &device).await;
+        Self::from_device(
+            &adapter,
+            device,
+            queue,
+            config,
+            RenderTarget::Surface(surface),
+            surface_caps.present_modes,
+            Some(window),
+            sample_count,
+        )
+        .await
+    }
+
+    /// creates a state without a window that renders into a texture,
+    /// which can be read back with [`State::read_pixels`]
+    ///
+    /// this doesn't need an X server, so it can be used in tests
+    pub async fn new_offscreen(width: u32, height: u32, format: wgpu::TextureFormat) -> State<'a> {
+        Self::offscreen(width, height, format, wgpu::CompositeAlphaMode::Opaque)
+            .await
+            .expect("Could not get adapter")
+    }
+
+    /// [`State::new_offscreen`] composited with `alpha_mode`,
+    /// or `None` if there's no adapter to render with
+    async fn offscreen(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        alpha_mode: wgpu::CompositeAlphaMode,
+    ) -> Option<State<'a>> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await?;
+
+        let (device, queue) = request_device(&adapter).await;
+
+        // there's no surface, but the configuration is still used
+        // to keep track of the target's size and format
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode,
+            view_formats: vec![],
+        };
+
+        let texture = create_offscreen_texture(&device, &config);
+
+        let state = Self::from_device(
+            &adapter,
+            device,
+            queue,
+            config,
+            RenderTarget::Offscreen(texture),
+            vec![wgpu::PresentMode::Fifo],
+            None,
+            1,
+        )
+        .await;
+
+        Some(state)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn from_device(
+        adapter: &wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        target: RenderTarget,
+        present_modes: Vec<wgpu::PresentMode>,
+        window: Option<Window<'a>>,
+        sample_count: u32,
+    ) -> State<'a> {
+        let (width, height) = (config.width, config.height);
+        let surface_format = config.format;
+
+        let sample_count = supported_sample_count(adapter, surface_format, sample_count);
+        let msaa_view =
+            (sample_count > 1).then(|| create_msaa_view(&device, &config, sample_count));
+
+        let mut renderer =
+            Renderer::new(config.format, config.alpha_mode, sample_count, &device).await;
+        renderer.update_uniforms(&queue, width, height);
 
         let mut font_system = FontSystem::new();
         let text_cache = SwashCache::new();
+        // glyphon keeps a separate RGBA atlas for color glyphs (emoji bitmaps),
+        // and cosmic-text falls back to an installed emoji font when shaping,
+        // so color emoji work as long as a font like Noto Color Emoji is installed
         let mut atlas = TextAtlas::new(&device, &queue, surface_format);
-        let text_renderer =
-            glyphon::TextRenderer::new(&mut atlas, &device, MultisampleState::default(), None);
+        let text_renderer = glyphon::TextRenderer::new(
+            &mut atlas,
+            &device,
+            MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            None,
+        );
 
         let mut measure_text_buffer = glyphon::Buffer::new(&mut font_system, Metrics::new(1., 1.));
 
@@ -150,20 +359,38 @@ impl<'a> State<'a> {
         };
 
         State {
-            surface,
+            target,
             device,
             queue,
             config,
+            present_modes,
             width,
             height,
             window,
             renderer,
             text_renderer,
+            adapter_info: adapter.get_info(),
+            sample_count,
+            msaa_view,
             clear_background: None,
             texts: Vec::new(),
+            owned_texts: HashMap::new(),
+            next_text_handle: 0,
+            prepared_size: (0, 0),
+            text_allocations: Vec::new(),
             meshes: Vec::new(),
+            uploaded_meshes: Vec::new(),
+            region: None,
+            uploaded_region: None,
+            textured_quads: Vec::new(),
+            background_texture: None,
+            icons: HashMap::new(),
+            clip_stack: Vec::new(),
+            translation_stack: Vec::new(),
+            painter: None,
             measure_text_buffer,
             text_cache: HashMap::new(),
+            frame: 0,
             default_font: Font::DEFAULT,
         }
     }
@@ -285,8 +512,83 @@ impl<'a> State<'a> {
         }
     }
 
-    pub fn window(&self) -> &Window {
-        &self.window
+    /// # Panics
+    ///
+    /// if the state was created with [`State::new_offscreen`]
+    pub fn window(&self) -> &Window<'a> {
+        self.window
+            .as_ref()
+            .expect("offscreen states don't have a window")
+    }
+
+    /// # Panics
+    ///
+    /// if the state was created with [`State::new_offscreen`]
+    pub fn window_mut(&mut self) -> &mut Window<'a> {
+        self.window
+            .as_mut()
+            .expect("offscreen states don't have a window")
+    }
+
+    /// the format frames are rendered in, see [`preferred_framebuffer_format`]
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// the GPU and graphics backend the state renders with, worth including
+    /// in reports about colors or rendering looking wrong
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    pub fn is_offscreen(&self) -> bool {
+        matches!(self.target, RenderTarget::Offscreen(_))
+    }
+
+    /// whether what was rendered last frame is still there when rendering the next one,
+    /// which is needed to only redraw part of the window with [`State::clear_region`]
+    ///
+    /// the multisampled texture and offscreen textures are kept between frames,
+    /// surface textures aren't
+    pub fn retains_frame(&self) -> bool {
+        self.msaa_view.is_some() || self.is_offscreen()
+    }
+
+    fn display_scale(&self) -> f32 {
+        self.window
+            .as_ref()
+            .map_or(1., |window| window.display_scale)
+    }
+
+    /// `color` in the form the surface stores and is composited with
+    fn clear_color(&self, color: crate::color::Color) -> wgpu::Color {
+        // clear colors skip the shaders, so they're decoded here for sRGB targets
+        let [r, g, b, a] = if self.config.format.is_srgb() {
+            color.rgba_linear_f32()
+        } else {
+            color.rgba_f32()
+        };
+        let [r, g, b] = if self.config.alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied {
+            [r * a, g * a, b * a]
+        } else {
+            [r, g, b]
+        };
+
+        wgpu::Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
+        }
+    }
+
+    fn configure_target(&mut self) {
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => {
+                *texture = create_offscreen_texture(&self.device, &self.config);
+            }
+        }
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -295,131 +597,377 @@ impl<'a> State<'a> {
             self.height = height;
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
+            self.configure_target();
+            if self.msaa_view.is_some() {
+                self.msaa_view = Some(create_msaa_view(
+                    &self.device,
+                    &self.config,
+                    self.sample_count,
+                ));
+            }
+            self.renderer
+                .update_uniforms(&self.queue, self.width, self.height);
         }
     }
 
-    pub fn update(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Update Render Encoder"),
-            });
+    /// handles the window geometry events of this window,
+    /// resizing the surface when the window's size changed
+    ///
+    /// returns `true` if the event was handled and the window should be redrawn
+    pub fn input(&mut self, event: &x11rb::protocol::Event) -> bool {
+        let Some(window) = &mut self.window else {
+            return false;
+        };
 
-        #[derive(Debug)]
-        enum Allocation {
-            Managed(Option<Arc<TextInner>>),
-            Cached(TextCacheKey),
-        }
+        match event {
+            x11rb::protocol::Event::ConfigureNotify(event) if event.window == window.xid => {
+                window.x = event.x.into();
+                window.y = event.y.into();
 
-        let texts = std::mem::take(&mut self.texts);
-        let allocations: Vec<Allocation> = texts
-            .iter()
-            .map(|t| match t {
-                TextTypes::Managed { text } => {
-                    let text = text.upgrade();
-                    Allocation::Managed(text)
+                let (width, height) = (event.width as u32, event.height as u32);
+                if width != self.width || height != self.height {
+                    window.width = width;
+                    window.height = height;
+                    self.resize(width, height);
                 }
-                TextTypes::Cached(text) => {
-                    let key = TextCacheKey {
-                        content: text.content.clone(),
-                        font_size: text.font_size.to_bits(),
-                        line_height: text.line_height.to_bits(),
-                        font: text.font,
-                        bounds: text.bounds,
-                        shaping: text.shaping,
-                    };
-                    if let Some(_) = self.text_cache.get(&key) {
-                        Allocation::Cached(key)
-                    } else {
-                        let mut buffer = glyphon::Buffer::new(
-                            &mut self.text_renderer.font_system,
-                            Metrics::new(text.font_size, text.line_height),
-                        );
 
-                        buffer.set_size(
-                            &mut self.text_renderer.font_system,
-                            self.width as f32,
-                            self.height as f32,
-                        );
+                true
+            }
+            x11rb::protocol::Event::Expose(event) if event.window == window.xid => {
+                // only redraw once for a series of exposes
+                event.count == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// reconfigures the surface with `present_mode`,
+    /// falling back to [`wgpu::PresentMode::Fifo`] if it's not supported
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        let present_mode = supported_present_mode(&self.present_modes, present_mode);
+        if present_mode != self.config.present_mode {
+            self.config.present_mode = present_mode;
+            self.configure_target();
+        }
+    }
 
-                        buffer.set_text(
+    pub fn update(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.gc_texts();
+        self.prepare_texts()?;
+
+        // texts with changing content would otherwise leave a buffer behind for every value
+        let frame = self.frame;
+        self.text_cache
+            .retain(|_, (_, last_used)| frame - *last_used < TEXT_CACHE_MAX_AGE);
+        self.frame += 1;
+
+        // an idle bar redraws the exact same shapes every frame,
+        // so only upload them when they actually changed, a frame without
+        // any shapes still replaces the ones of the last frame
+        let meshes_changed = self.meshes != self.uploaded_meshes;
+        if meshes_changed {
+            std::mem::swap(&mut self.meshes, &mut self.uploaded_meshes);
+        }
+        self.meshes.clear();
+
+        let painter_changed = self.painter.as_mut().is_some_and(Painter::take_dirty);
+
+        // retained meshes aren't drawn under any clip, so keep them inside
+        // the redrawn region like everything else
+        let region = self.region.take();
+        let region_changed = region != self.uploaded_region;
+        self.uploaded_region = region;
+
+        if meshes_changed || painter_changed || region_changed {
+            let meshes: Vec<(&Mesh, f32, Option<ClipRect>)> = self
+                .painter
+                .iter()
+                .flat_map(Painter::meshes)
+                .map(|(mesh, z)| (mesh, z, region))
+                .chain(
+                    self.uploaded_meshes
+                        .iter()
+                        .map(|(mesh, z, clip)| (mesh, *z, *clip)),
+                )
+                .collect();
+
+            self.renderer
+                .update_buffers(&self.device, &self.queue, meshes);
+        }
+
+        let background = self.background_texture.clone().map(|texture| TexturedQuad {
+            texture,
+            x: 0.,
+            y: 0.,
+            width: self.width as f32,
+            height: self.height as f32,
+            clip: None,
+        });
+        let background_quads = background.is_some() as usize;
+        self.textured_quads.splice(0..0, background);
+
+        self.renderer.update_textures(
+            &self.device,
+            &self.queue,
+            &self.textured_quads,
+            background_quads,
+        );
+        self.textured_quads.clear();
+
+        Ok(())
+    }
+
+    /// fills the text cache and prepares the queued texts for the current size
+    fn prepare_texts(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let display_scale = self.display_scale();
+        // taken so the cache can be filled while going over them,
+        // and put back afterwards until the frame is rendered
+        let texts = std::mem::take(&mut self.texts);
+        let mut allocations = std::mem::take(&mut self.text_allocations);
+        allocations.extend(texts.iter().map(|t| match t {
+            TextTypes::Managed { text, .. } => {
+                let text = text.upgrade();
+                Allocation::Managed(text)
+            }
+            TextTypes::Cached(text) => {
+                let key = TextCacheKey {
+                    content: text.content.clone(),
+                    font_size: text.font_size.to_bits(),
+                    line_height: text.line_height.to_bits(),
+                    font: text.font,
+                    font_stack: text.font_stack.clone(),
+                    colors: text.colors.clone(),
+                    shaping: text.shaping,
+                };
+                if let Some((_, last_used)) = self.text_cache.get_mut(&key) {
+                    *last_used = self.frame;
+                    Allocation::Cached(key)
+                } else {
+                    let mut buffer = glyphon::Buffer::new(
+                        &mut self.text_renderer.font_system,
+                        Metrics::new(text.font_size, text.line_height),
+                    );
+
+                    buffer.set_size(
+                        &mut self.text_renderer.font_system,
+                        self.width as f32,
+                        self.height as f32,
+                    );
+
+                    // the color and position aren't part of the key,
+                    // they're applied when the buffer is drawn, through `default_color`
+                    let attrs = text.font.attrs();
+                    match (&text.font_stack, &text.colors) {
+                        (_, Some(colors)) => {
+                            let mut start = 0;
+                            let spans = colors.iter().map(|(len, color)| {
+                                let end = (start + len).min(text.content.len());
+                                let span = &text.content[start..end];
+                                start = end;
+                                (span, attrs.color((*color).into()))
+                            });
+                            buffer.set_rich_text(
+                                &mut self.text_renderer.font_system,
+                                spans,
+                                text.shaping,
+                            );
+                        }
+                        (Some(font_stack), None) => {
+                            let spans = font_stack.spans(
+                                &mut self.text_renderer.font_system,
+                                &text.content,
+                                attrs,
+                            );
+                            buffer.set_rich_text(
+                                &mut self.text_renderer.font_system,
+                                spans,
+                                text.shaping,
+                            );
+                        }
+                        (None, None) => buffer.set_text(
                             &mut self.text_renderer.font_system,
                             &text.content,
-                            Attrs::new().color(text.color.into()),
+                            attrs,
                             text.shaping,
-                        );
-
-                        self.text_cache.insert(key.clone(), buffer);
-                        Allocation::Cached(key)
+                        ),
                     }
+
+                    // for RTL text to start at `x` too
+                    fit_to_text(&mut buffer, &mut self.text_renderer.font_system);
+
+                    self.text_cache.insert(key.clone(), (buffer, self.frame));
+                    Allocation::Cached(key)
                 }
-            })
-            .collect();
+            }
+            TextTypes::Owned { handle, .. } => Allocation::Owned(*handle),
+        }));
 
-        let texts = texts
+        let text_areas = texts
             .iter()
             .zip(allocations.iter())
             .filter_map(|(text, allocation)| match text {
-                TextTypes::Managed { .. } => {
-                    let Allocation::Managed(Some(text)) = allocation else {
-                        return None;
+                TextTypes::Managed {
+                    clip,
+                    translation: (dx, dy),
+                    ..
+                }
+                | TextTypes::Owned {
+                    clip,
+                    translation: (dx, dy),
+                    ..
+                } => {
+                    let text = match allocation {
+                        Allocation::Managed(Some(text)) => text.as_ref(),
+                        Allocation::Owned(handle) => self.owned_texts.get(handle)?,
+                        _ => return None,
+                    };
+
+                    let bounds = glyphon::TextBounds {
+                        left: text.bounds.left + *dx as i32,
+                        top: text.bounds.top + *dy as i32,
+                        right: text.bounds.right + *dx as i32,
+                        bottom: text.bounds.bottom + *dy as i32,
                     };
 
                     Some(TextArea {
                         buffer: &text.buffer,
-                        left: text.x,
-                        top: text.y,
-                        scale: self.window.display_scale,
-                        bounds: text.bounds,
+                        left: text.x + dx,
+                        top: text.y + dy,
+                        scale: display_scale,
+                        bounds: clip_text_bounds(bounds, clip.as_ref()),
                         default_color: text.color.into(),
                     })
                 }
                 TextTypes::Cached(text) => {
                     let Allocation::Cached(key) = allocation else {
-                            return None;
-                        };
-                    let buffer = self.text_cache.get(key).expect("Get cached buffer");
+                        return None;
+                    };
+                    let (buffer, _) = self.text_cache.get(key).expect("Get cached buffer");
 
                     Some(TextArea {
                         buffer,
                         left: text.x,
                         top: text.y,
-                        scale: self.window.display_scale,
+                        scale: display_scale,
                         bounds: text.bounds,
                         default_color: text.color.into(),
                     })
                 }
-            })
-            .collect();
-
-        self.text_renderer
-            .prepare(&self.device, &self.queue, self.width, self.height, texts)?;
-
-        let meshes = std::mem::take(&mut self.meshes);
+            });
 
-        self.renderer.update_buffers(
+        self.text_renderer.prepare(
             &self.device,
             &self.queue,
-            &mut encoder,
-            meshes,
             self.width,
             self.height,
-        );
+            text_areas,
+        )?;
+
+        self.texts = texts;
+        allocations.clear();
+        self.text_allocations = allocations;
+        self.prepared_size = (self.width, self.height);
 
         Ok(())
     }
 
+    /// clears the window with `color` before drawing anything
+    ///
+    /// an alpha below 255 only shows the desktop behind the window if it has
+    /// a 32-bit visual and a compositor is running, see [`Window::transparent`]
     pub fn clear_background(&mut self, color: crate::color::Color) {
         self.clear_background = Some(color);
     }
 
+    /// drops the shapes queued since the last [`State::update`]
+    pub fn clear_shapes(&mut self) {
+        self.meshes.clear();
+    }
+
+    /// drops the texts queued since the last frame
+    pub fn clear_texts(&mut self) {
+        self.texts.clear();
+    }
+
+    /// drops the queued texts from [`State::draw_text_absolute`] whose [`TextInner`]
+    /// was dropped since, called by [`State::update`] before preparing them
+    pub fn gc_texts(&mut self) {
+        self.texts.retain(|text| match text {
+            TextTypes::Managed { text, .. } => text.raw.strong_count() > 0,
+            TextTypes::Owned { handle, .. } => self.owned_texts.contains_key(handle),
+            TextTypes::Cached(_) => true,
+        });
+    }
+
+    /// drops everything queued for the next frame, to start drawing it from scratch
+    pub fn clear_all(&mut self) {
+        self.clear_shapes();
+        self.clear_texts();
+        self.textured_quads.clear();
+        self.clear_background = None;
+    }
+
+    /// fills `rect` with `color` under everything else drawn this frame,
+    /// instead of clearing the whole window with [`State::clear_background`]
+    ///
+    /// the rest of the window keeps the last frame only if [`State::retains_frame`],
+    /// and `color` has to be opaque to completely cover what was there
+    pub fn clear_region(&mut self, rect: ClipRect, color: crate::color::Color) {
+        self.region = Some(match self.region {
+            Some(region) => region.union(&rect),
+            None => rect,
+        });
+
+        self.push_clip(rect);
+        self.draw_shape_absolute_with_z(
+            Shape::Rect(Rect {
+                x: rect.x.floor(),
+                y: rect.y.floor(),
+                width: rect.width.ceil() as u32 + 1,
+                height: rect.height.ceil() as u32 + 1,
+                color,
+            }),
+            f32::MIN,
+        );
+        self.pop_clip();
+    }
+
+    /// renders what was queued since the last frame, [`State::update`] has to be called first
+    ///
+    /// if the window was resized since then, the texts are prepared again for the new size
+    /// instead of presenting a frame laid out for the old one
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+        let result = self.render_frame();
+        self.texts.clear();
+        result
+    }
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    fn render_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if self.prepared_size != (self.width, self.height) {
+            self.prepare_texts()?;
+        }
+
+        let (output, view) = match &self.target {
+            RenderTarget::Surface(surface) => {
+                let output = match surface.get_current_texture() {
+                    // the surface no longer matches the window, usually in the middle of
+                    // a resize, so reconfigure it and try once more before giving up on the frame
+                    Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                        surface.configure(&self.device, &self.config);
+                        surface.get_current_texture()?
+                    }
+                    result => result?,
+                };
+                let view = output
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                (Some(output), view)
+            }
+            RenderTarget::Offscreen(texture) => (
+                None,
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            ),
+        };
 
         let mut encoder = self
             .device
@@ -427,14 +975,21 @@ impl<'a> State<'a> {
                 label: Some("Render Encoder"),
             });
 
+        // with MSAA everything is drawn into the multisampled texture
+        // which is then resolved into the surface texture
+        let (target, resolve_target) = match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(&view)),
+            None => (&view, None),
+        };
+
         if let Some(color) = self.clear_background.take() {
             encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Clear Background Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: target,
+                    resolve_target,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(color.into()),
+                        load: wgpu::LoadOp::Clear(self.clear_color(color)),
                         store: true,
                     },
                 })],
@@ -446,8 +1001,8 @@ impl<'a> State<'a> {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Mesh Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: target,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
                         store: true,
@@ -463,8 +1018,8 @@ impl<'a> State<'a> {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Text Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: target,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
                         store: true,
@@ -477,71 +1032,354 @@ impl<'a> State<'a> {
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        if let Some(output) = output {
+            output.present();
+        }
 
         self.text_renderer.trim();
 
         Ok(())
     }
 
+    /// copies the last rendered frame of an offscreen state to the CPU,
+    /// the pixels are tightly packed rows in the format the state was created with
+    ///
+    /// returns `None` if the state renders to a window
+    pub fn read_pixels(&self) -> Option<Vec<u8>> {
+        let RenderTarget::Offscreen(texture) = &self.target else {
+            return None;
+        };
+
+        let bytes_per_pixel = self.config.format.block_size(None)?;
+        let unpadded_bytes_per_row = self.width * bytes_per_pixel;
+        // rows in a texture to buffer copy have to be aligned
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Read Pixels Buffer"),
+            size: (padded_bytes_per_row * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Read Pixels Encoder"),
+            });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            texture.size(),
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+
+        buffer.unmap();
+
+        Some(pixels)
+    }
+
+    /// [`State::read_pixels`] as an image, for snapshot testing
+    /// with [`snapshot::compare_with_golden`]
+    ///
+    /// returns `None` if the state renders to a window,
+    /// or its format isn't an 8-bit RGBA or BGRA one
+    pub fn capture_frame(&self) -> Option<image::RgbaImage> {
+        let mut pixels = self.read_pixels()?;
+
+        match self.config.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {}
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            _ => return None,
+        }
+
+        image::RgbaImage::from_raw(self.width, self.height, pixels)
+    }
+
     /// draws a shape in an absolute position
     pub fn draw_shape_absolute(&mut self, shape: Shape) {
-        match shape {
-            Shape::Rect(rect) => {
-                let color = rect.color.rgb_f32();
-                self.meshes.push(Mesh {
-                    indices: vec![0, 1, 2, 0, 2, 3],
-                    vertices: vec![
-                        VertexColored {
-                            position: [rect.x as f32, rect.y as f32, 0.],
-                            color,
-                        },
-                        VertexColored {
-                            position: [rect.x as f32, rect.y as f32 + rect.height as f32, 0.],
-                            color,
-                        },
-                        VertexColored {
-                            position: [
-                                rect.x as f32 + rect.width as f32,
-                                rect.y as f32 + rect.height as f32,
-                                0.,
-                            ],
-                            color,
-                        },
-                        VertexColored {
-                            position: [rect.x as f32 + rect.width as f32, rect.y as f32, 0.],
-                            color,
-                        },
-                    ],
-                });
-            }
-            Shape::Triangle(triangle) => {
-                let color = triangle.color.rgb_f32();
-                self.meshes.push(Mesh {
-                    indices: vec![0, 1, 2],
-                    vertices: vec![
-                        VertexColored {
-                            position: [triangle.a.0 as f32, triangle.a.1 as f32, 0.],
-                            color,
-                        },
-                        VertexColored {
-                            position: [triangle.b.0 as f32, triangle.b.1 as f32, 0.],
-                            color,
-                        },
-                        VertexColored {
-                            position: [triangle.c.0 as f32, triangle.c.1 as f32, 0.],
-                            color,
-                        },
-                    ],
-                });
-            }
-            Shape::Circle(circle) => {
-                let color = circle.color.rgb_f32();
-                let (vertices, indices) =
-                    create_circle_vertices(circle.radius, 30, color, circle.x, circle.y);
-                self.meshes.push(Mesh { indices, vertices });
+        self.draw_shape_absolute_with_z(shape, 0.);
+    }
+
+    /// draws a shape in an absolute position with an explicit draw order
+    ///
+    /// shapes with a higher `z` are drawn over the ones with a lower `z`,
+    /// shapes with the same `z` are drawn in the order they were drawn in
+    pub fn draw_shape_absolute_with_z(&mut self, shape: Shape, z: f32) {
+        self.draw_mesh_absolute(Self::create_mesh(shape), z);
+    }
+
+    fn draw_mesh_absolute(&mut self, mut mesh: Mesh, z: f32) {
+        let clip = self.clip_stack.last().copied();
+
+        let (dx, dy) = self.translation();
+        if dx != 0. || dy != 0. {
+            for vertex in mesh.vertices.iter_mut() {
+                vertex.position[0] += dx;
+                vertex.position[1] += dy;
             }
         }
+
+        self.meshes.push((mesh, z, clip));
+    }
+
+    /// draws the background of the bar, and the filled part over it
+    pub fn draw_progress_bar(&mut self, bar: &ProgressBar) {
+        let (background, foreground) = bar.rects();
+
+        self.draw_shape_absolute(Shape::Rect(background));
+        if foreground.width > 0 {
+            self.draw_shape_absolute(Shape::Rect(foreground));
+        }
+    }
+
+    /// draws the history in `samples` as a filled line graph over `area`,
+    /// see [`Mesh::sparkline`]
+    pub fn draw_sparkline(&mut self, samples: &[f32], area: &Rect) {
+        if samples.is_empty() {
+            return;
+        }
+
+        self.draw_mesh_absolute(Mesh::sparkline(samples, area), 0.);
+    }
+
+    /// draws `texture` stretched over the whole window under the shapes and texts of every
+    /// frame, like a wallpaper, until it's replaced or `None` is set
+    pub fn set_background_texture(&mut self, texture: Option<Arc<GpuTexture>>) {
+        self.background_texture = texture;
+    }
+
+    pub fn background_texture(&self) -> Option<&Arc<GpuTexture>> {
+        self.background_texture.as_ref()
+    }
+
+    /// uploads `image` and draws it stretched over the given rect
+    ///
+    /// textured quads are drawn over all the shapes, the image is uploaded
+    /// every time this is called, so prefer [`State::draw_gpu_texture`]
+    /// for images that are drawn every frame
+    pub fn draw_texture(
+        &mut self,
+        image: &image::RgbaImage,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) {
+        if let Some(texture) = self.create_texture(image) {
+            self.draw_gpu_texture(texture, x, y, width, height);
+        }
+    }
+
+    /// uploads `image` to the GPU so it can be drawn with [`State::draw_gpu_texture`],
+    /// returns `None` for an empty image
+    pub fn create_texture(&self, image: &image::RgbaImage) -> Option<Arc<GpuTexture>> {
+        self.renderer
+            .create_texture(&self.device, &self.queue, image)
+            .map(Arc::new)
+    }
+
+    /// draws an already uploaded texture stretched over the given rect
+    pub fn draw_gpu_texture(
+        &mut self,
+        texture: Arc<GpuTexture>,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) {
+        let (dx, dy) = self.translation();
+        self.textured_quads.push(TexturedQuad {
+            texture,
+            x: x + dx,
+            y: y + dy,
+            width,
+            height,
+            clip: self.clip_stack.last().copied(),
+        });
+    }
+
+    /// decodes and uploads the icon at `path`,
+    /// or returns the cached one if it's already loaded, an empty image is an error
+    pub fn load_icon(&mut self, path: impl AsRef<Path>) -> Result<IconHandle, image::ImageError> {
+        let path = path.as_ref();
+
+        if !self.icons.contains_key(path) {
+            let image = image::open(path)?.into_rgba8();
+            let texture = self.create_texture(&image).ok_or_else(|| {
+                image::ImageError::Parameter(image::error::ParameterError::from_kind(
+                    image::error::ParameterErrorKind::DimensionMismatch,
+                ))
+            })?;
+            self.icons.insert(path.to_path_buf(), texture);
+        }
+
+        Ok(IconHandle(path.to_path_buf()))
+    }
+
+    /// draws a loaded icon as a `size`x`size` square
+    ///
+    /// does nothing if the icon was unloaded
+    pub fn draw_icon(&mut self, handle: &IconHandle, x: f32, y: f32, size: f32) {
+        let Some(texture) = self.icons.get(&handle.0).cloned() else {
+            return;
+        };
+
+        self.draw_gpu_texture(texture, x, y, size, size);
+    }
+
+    /// evicts the icon from the cache, it'll be decoded again
+    /// the next time it's loaded
+    pub fn unload_icon(&mut self, handle: &IconHandle) {
+        self.icons.remove(&handle.0);
+    }
+
+    /// clips everything drawn until the matching [`State::pop_clip`] to `rect`
+    ///
+    /// `rect` is relative to the current translation,
+    /// nested clips are intersected with the outer ones
+    pub fn push_clip(&mut self, rect: ClipRect) {
+        let (dx, dy) = self.translation();
+        let rect = ClipRect {
+            x: rect.x + dx,
+            y: rect.y + dy,
+            ..rect
+        };
+
+        let rect = match self.clip_stack.last() {
+            Some(outer) => outer.intersect(&rect),
+            None => rect,
+        };
+
+        self.clip_stack.push(rect);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clip_stack.pop();
+    }
+
+    /// offsets everything drawn until the matching [`State::pop_translation`],
+    /// so it can be drawn in local coordinates
+    ///
+    /// nested translations add up
+    pub fn push_translation(&mut self, dx: f32, dy: f32) {
+        let (x, y) = self.translation();
+        self.translation_stack.push((x + dx, y + dy));
+    }
+
+    pub fn pop_translation(&mut self) {
+        self.translation_stack.pop();
+    }
+
+    /// the current total translation
+    pub fn translation(&self) -> (f32, f32) {
+        self.translation_stack.last().copied().unwrap_or((0., 0.))
+    }
+
+    /// the retained-mode painter, created on first use
+    ///
+    /// meshes added to it are drawn every frame, below the shapes drawn
+    /// with [`State::draw_shape_absolute`] that have the same z order
+    pub fn painter_mut(&mut self) -> &mut Painter {
+        self.painter.get_or_insert_with(Painter::new)
+    }
+
+    /// creates a text owned by the state, for texts that change often like clocks,
+    /// it's only drawn when queued with [`State::draw_text`]
+    ///
+    /// the text is kept until [`State::remove_text`], widgets only hold the handle
+    pub fn create_text(
+        &mut self,
+        content: &str,
+        font_size: f32,
+        color: crate::color::Color,
+    ) -> TextHandle {
+        let scale = self.display_scale();
+        let text = TextInner::new(
+            &mut self.text_renderer.font_system,
+            content,
+            0.,
+            0.,
+            self.width as f32 * scale,
+            self.height as f32 * scale,
+            font_size,
+            color,
+            self.default_font,
+        );
+
+        let handle = TextHandle(self.next_text_handle);
+        self.next_text_handle += 1;
+        self.owned_texts.insert(handle, text);
+
+        handle
+    }
+
+    /// replaces the content of a text from [`State::create_text`],
+    /// it's only reshaped if the content changed
+    ///
+    /// returns `false` if the handle was already removed
+    pub fn update_text(&mut self, handle: TextHandle, content: &str) -> bool {
+        let Some(text) = self.owned_texts.get_mut(&handle) else {
+            return false;
+        };
+
+        text.set_content(&mut self.text_renderer.font_system, content);
+
+        true
+    }
+
+    /// queues a text from [`State::create_text`] at `x` and `y` for this frame
+    pub fn draw_text(&mut self, handle: TextHandle, x: f32, y: f32) {
+        let Some(text) = self.owned_texts.get_mut(&handle) else {
+            return;
+        };
+
+        text.set_position(x, y);
+        self.texts.push(TextTypes::Owned {
+            handle,
+            clip: self.clip_stack.last().copied(),
+            translation: self.translation(),
+        });
+    }
+
+    /// the width and height of a text from [`State::create_text`]
+    pub fn text_size(&self, handle: TextHandle) -> Option<(f32, f32)> {
+        self.owned_texts
+            .get(&handle)
+            .map(|text| measure_text(&text.buffer))
+    }
+
+    pub fn remove_text(&mut self, handle: TextHandle) {
+        self.owned_texts.remove(&handle);
     }
 
     pub fn draw_text_absolute(&mut self, text: Arc<TextInner>) {
@@ -549,6 +1387,8 @@ impl<'a> State<'a> {
             text: ManagedText {
                 raw: Arc::downgrade(&text),
             },
+            clip: self.clip_stack.last().copied(),
+            translation: self.translation(),
         });
     }
 
@@ -566,22 +1406,187 @@ impl<'a> State<'a> {
         color: crate::color::Color,
         font_size: f32,
     ) {
+        self.draw_text_absolute_cached_with_font(
+            content,
+            x,
+            y,
+            color,
+            font_size,
+            self.default_font,
+            Shaping::Advanced,
+        );
+    }
+
+    /// same as [`State::draw_text_absolute_cached`] with a specific font and shaping,
+    /// [`Shaping::Basic`] is faster for text without ligatures or complex scripts, like digits
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_absolute_cached_with_font(
+        &mut self,
+        content: &str,
+        x: f32,
+        y: f32,
+        color: crate::color::Color,
+        font_size: f32,
+        font: Font,
+        shaping: Shaping,
+    ) {
+        let (dx, dy) = self.translation();
+        let (x, y) = (x + dx, y + dy);
         self.texts.push(TextTypes::Cached(CachedText {
             x,
             y,
             content: content.to_string(),
-            bounds: glyphon::TextBounds {
-                left: x as i32,
-                top: y as i32,
-                right: self.width as i32,
-                bottom: self.height as i32,
-            },
+            bounds: clip_text_bounds(
+                glyphon::TextBounds {
+                    left: x as i32,
+                    top: y as i32,
+                    right: self.width as i32,
+                    bottom: self.height as i32,
+                },
+                self.clip_stack.last(),
+            ),
             color,
             font_size,
             line_height: font_size,
-            font: self.default_font,
-            shaping: Shaping::Advanced,
+            font,
+            font_stack: None,
+            colors: None,
+            shaping,
+        }));
+    }
+
+    /// same as [`State::draw_text_absolute_cached`], but glyphs are taken from the first
+    /// family in `font_stack` that has them, instead of only the default font
+    pub fn draw_text_absolute_cached_with_stack(
+        &mut self,
+        content: &str,
+        x: f32,
+        y: f32,
+        color: crate::color::Color,
+        font_size: f32,
+        font_stack: &FontStack,
+    ) {
+        self.draw_text_absolute_cached(content, x, y, color, font_size);
+
+        if let Some(TextTypes::Cached(text)) = self.texts.last_mut() {
+            text.font_stack = Some(font_stack.clone());
+        }
+    }
+
+    /// draws the spans one after the other in their own colors, like an icon and a number
+    /// in different colors, they're shaped as one text so kerning across them is kept
+    ///
+    /// it's as wide as [`State::measure_text`] of the spans joined together
+    pub fn draw_rich_text(
+        &mut self,
+        spans: &[(String, crate::color::Color)],
+        x: f32,
+        y: f32,
+        font_size: f32,
+    ) {
+        let Some((_, first_color)) = spans.first() else {
+            return;
+        };
+
+        let content: String = spans.iter().map(|(text, _)| text.as_str()).collect();
+        self.draw_text_absolute_cached(&content, x, y, *first_color, font_size);
+
+        if let Some(TextTypes::Cached(text)) = self.texts.last_mut() {
+            text.colors = Some(
+                spans
+                    .iter()
+                    .map(|(text, color)| (text.len(), *color))
+                    .collect(),
+            );
+        }
+    }
+
+    /// same as [`State::draw_text_absolute_cached`], with lines drawn over the text
+    ///
+    /// the lines are as wide as the text, the underline sits a bit below the baseline
+    pub fn draw_text_absolute_cached_decorated(
+        &mut self,
+        content: &str,
+        x: f32,
+        y: f32,
+        color: crate::color::Color,
+        font_size: f32,
+        decoration: TextDecoration,
+    ) {
+        self.draw_text_absolute_cached(content, x, y, color, font_size);
+
+        if decoration == TextDecoration::NONE {
+            return;
+        }
+
+        // measured with the same attributes the cached buffer is created with
+        self.measure_text_buffer.set_metrics(
+            &mut self.text_renderer.font_system,
+            Metrics::new(font_size, font_size),
+        );
+        self.measure_text_buffer.set_text(
+            &mut self.text_renderer.font_system,
+            content,
+            self.default_font.attrs(),
+            Shaping::Advanced,
+        );
+
+        let (width, _) = measure_text(&self.measure_text_buffer);
+        let Some(baseline) = self
+            .measure_text_buffer
+            .layout_runs()
+            .next()
+            .map(|run| run.line_y)
+        else {
+            return;
+        };
+
+        let thickness = (font_size / 14.).round().max(1.);
+        let line = |line_y: f32| {
+            Shape::Rect(Rect {
+                x,
+                y: y + line_y,
+                width: width.ceil() as u32,
+                height: thickness as u32,
+                color,
+            })
+        };
+
+        if decoration.underline {
+            self.draw_shape_absolute(line(baseline + 2.));
+        }
+
+        if decoration.strikethrough {
+            // roughly the middle of lowercase letters
+            self.draw_shape_absolute(line(baseline - font_size * 0.3));
+        }
+    }
+
+    /// same as [`State::draw_text_absolute_cached`], on a box of `background` that's
+    /// `padding` bigger than the text on every side, `x` and `y` are the box's corner
+    ///
+    /// texts are rendered after every shape, so the box is always behind the text
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_boxed(
+        &mut self,
+        content: &str,
+        x: f32,
+        y: f32,
+        color: crate::color::Color,
+        background: crate::color::Color,
+        padding: f32,
+        font_size: f32,
+    ) {
+        let (width, height) = self.measure_text(content, Metrics::new(font_size, font_size));
+
+        self.draw_shape_absolute(Shape::Rect(Rect {
+            x,
+            y,
+            width: (width + 2. * padding).ceil() as u32,
+            height: (height + 2. * padding).ceil() as u32,
+            color: background,
         }));
+        self.draw_text_absolute_cached(content, x + padding, y + padding, color, font_size);
     }
 
     pub fn measure_text(&mut self, text: &str, metrics: Metrics) -> (f32, f32) {
@@ -603,6 +1608,95 @@ impl<'a> State<'a> {
     }
 }
 
+fn clip_text_bounds(bounds: glyphon::TextBounds, clip: Option<&ClipRect>) -> glyphon::TextBounds {
+    let Some(clip) = clip else {
+        return bounds;
+    };
+
+    glyphon::TextBounds {
+        left: bounds.left.max(clip.x.floor() as i32),
+        top: bounds.top.max(clip.y.floor() as i32),
+        right: bounds.right.min((clip.x + clip.width).ceil() as i32),
+        bottom: bounds.bottom.min((clip.y + clip.height).ceil() as i32),
+    }
+}
+
+fn supported_sample_count(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    requested: u32,
+) -> u32 {
+    let flags = adapter.get_texture_format_features(format).flags;
+    if matches!(requested, 2 | 4 | 8) && flags.sample_count_supported(requested) {
+        requested
+    } else {
+        1
+    }
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                features: wgpu::Features::empty(),
+                // WebGL doesn't support all of wgpu's features, so if
+                // we're building for the web we'll have to disable some.
+                limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::default()
+                },
+                label: None,
+            },
+            None, // Trace path
+        )
+        .await
+        .unwrap()
+}
+
+fn create_offscreen_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    })
+}
+
+fn create_msaa_view(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
 fn create_circle_vertices(
     radius: f32,
     num_segments: u32,
@@ -660,6 +1754,42 @@ pub fn preferred_framebuffer_format(
         .ok_or(WgpuError::NoSurfaceFormatsAvailable)
 }
 
+/// Returns `requested` if it's one of the supported `present_modes`,
+/// otherwise [`wgpu::PresentMode::Fifo`] which every surface supports.
+pub fn supported_present_mode(
+    present_modes: &[wgpu::PresentMode],
+    requested: wgpu::PresentMode,
+) -> wgpu::PresentMode {
+    if present_modes.contains(&requested) {
+        requested
+    } else {
+        wgpu::PresentMode::Fifo
+    }
+}
+
+/// Picks a compositing alpha mode that blends the window with what's behind it
+/// when the window is `transparent`, falling back to the first supported mode.
+///
+/// Blending only works if the window was created with a 32-bit visual,
+/// see [`Window::transparent`].
+pub fn preferred_alpha_mode(
+    alpha_modes: &[wgpu::CompositeAlphaMode],
+    transparent: bool,
+) -> wgpu::CompositeAlphaMode {
+    if transparent {
+        for mode in [
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+        ] {
+            if alpha_modes.contains(&mode) {
+                return mode;
+            }
+        }
+    }
+
+    alpha_modes[0]
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum WgpuError {
     #[error("Failed to create wgpu adapter, no suitable adapter found.")]
@@ -674,3 +1804,186 @@ pub enum WgpuError {
     #[error(transparent)]
     CreateSurfaceError(#[from] wgpu::CreateSurfaceError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    /// an offscreen state, the tests that render are ignored by default since they need
+    /// a GPU or a software renderer, so they fail instead of passing untested without one
+    fn offscreen(width: u32, height: u32, format: wgpu::TextureFormat) -> State<'static> {
+        offscreen_with_alpha(width, height, format, wgpu::CompositeAlphaMode::Opaque)
+    }
+
+    fn offscreen_with_alpha(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        alpha_mode: wgpu::CompositeAlphaMode,
+    ) -> State<'static> {
+        pollster::block_on(State::offscreen(width, height, format, alpha_mode))
+            .expect("no adapter to render with")
+    }
+
+    fn red_rect(x: f32) -> Shape {
+        Shape::Rect(Rect {
+            x,
+            y: 2.,
+            width: 4,
+            height: 4,
+            color: Color::rgb(255, 0, 0),
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn identical_frames_upload_once() {
+        let mut state = offscreen(8, 8, wgpu::TextureFormat::Rgba8Unorm);
+
+        for _ in 0..2 {
+            state.draw_shape_absolute(red_rect(2.));
+            state.update().unwrap();
+            state.render().unwrap();
+        }
+        assert_eq!(state.renderer.buffer_updates(), 1);
+
+        // a changed frame is uploaded again
+        state.draw_shape_absolute(red_rect(1.));
+        state.update().unwrap();
+        assert_eq!(state.renderer.buffer_updates(), 2);
+
+        // and so is one without any shapes, even without `clear_shapes`
+        state.update().unwrap();
+        assert_eq!(state.renderer.buffer_updates(), 3);
+        state.update().unwrap();
+        assert_eq!(state.renderer.buffer_updates(), 3);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn empty_images_are_not_uploaded() {
+        let mut state = offscreen(8, 8, wgpu::TextureFormat::Rgba8Unorm);
+
+        assert!(state.create_texture(&image::RgbaImage::new(0, 0)).is_none());
+
+        // drawing one is skipped instead of panicking
+        state.draw_texture(&image::RgbaImage::new(0, 4), 0., 0., 4., 4.);
+        state.update().unwrap();
+        state.render().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn straight_and_premultiplied_clear() {
+        let half_red = Color::rgba(255, 0, 0, 128);
+
+        for (alpha_mode, expected) in [
+            (wgpu::CompositeAlphaMode::PostMultiplied, [255, 0, 0, 128]),
+            (wgpu::CompositeAlphaMode::PreMultiplied, [128, 0, 0, 128]),
+        ] {
+            let mut state = offscreen_with_alpha(4, 4, wgpu::TextureFormat::Rgba8Unorm, alpha_mode);
+
+            state.clear_background(half_red);
+            state.update().unwrap();
+            state.render().unwrap();
+
+            let pixels = state.read_pixels().unwrap();
+            assert_eq!(pixels[..4], expected, "{alpha_mode:?}");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn mid_gray_reads_back_the_same_on_srgb_and_linear_targets() {
+        let gray = Color::rgb(128, 128, 128);
+
+        for format in [
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        ] {
+            let mut state = offscreen(8, 8, format);
+
+            // the clear color skips the shaders, the rect goes through them
+            state.clear_background(gray);
+            state.draw_shape_absolute(Shape::Rect(Rect {
+                x: 4.,
+                y: 0.,
+                width: 4,
+                height: 8,
+                color: gray,
+            }));
+            state.update().unwrap();
+            state.render().unwrap();
+
+            let pixels = state.read_pixels().unwrap();
+            for x in [1, 6] {
+                let pixel = &pixels[x * 4..x * 4 + 4];
+                assert!(
+                    pixel[..3].iter().all(|&c| c.abs_diff(128) <= 1),
+                    "{format:?} at x = {x}: {pixel:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn red_rect_renders_red_pixels() {
+        let mut state = offscreen(8, 8, wgpu::TextureFormat::Rgba8Unorm);
+
+        state.clear_background(Color::rgb(0, 0, 255));
+        state.draw_shape_absolute(red_rect(2.));
+        state.update().unwrap();
+        state.render().unwrap();
+
+        let frame = state.capture_frame().unwrap();
+        assert_eq!(frame.get_pixel(3, 3).0, [255, 0, 0, 255]);
+        assert_eq!(frame.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(frame.get_pixel(6, 6).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn rect_and_circle_snapshot() {
+        let mut state = offscreen(48, 32, wgpu::TextureFormat::Rgba8Unorm);
+
+        state.clear_background(Color::rgb(30, 30, 46));
+        state.draw_shape_absolute(Shape::Rect(Rect {
+            x: 4.,
+            y: 4.,
+            width: 20,
+            height: 24,
+            color: Color::rgb(233, 86, 120),
+        }));
+        state.draw_shape_absolute(Shape::Circle(shapes::Circle {
+            x: 32.,
+            y: 16.,
+            radius: 11.,
+            color: Color::rgb(137, 180, 250),
+        }));
+        state.update().unwrap();
+        state.render().unwrap();
+
+        let frame = state.capture_frame().unwrap();
+        let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/rect_circle.png");
+        snapshot::compare_with_golden(&frame, golden, 2).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn mixed_text_and_emoji() {
+        let mut state = offscreen(160, 24, wgpu::TextureFormat::Rgba8Unorm);
+
+        let content = "bat 🔋 80% vol 🔊";
+        let (width, _) = state.measure_text(content, Metrics::new(16., 16.));
+        let (text_width, _) = state.measure_text("bat ", Metrics::new(16., 16.));
+        assert!(width > text_width);
+
+        // color glyphs go through the color atlas, which shouldn't panic
+        // whether or not an emoji font is installed
+        state.draw_text_absolute_cached(content, 0., 0., Color::rgb(255, 255, 255), 16.);
+        state.update().unwrap();
+        state.render().unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+use indexmap::IndexMap;
+
+use crate::{
+    shapes::{Mesh, Shape},
+    State,
+};
+
+/// A handle to a mesh retained by a [`Painter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshHandle(u64);
+
+/// Retained-mode counterpart to [`State::draw_shape_absolute`]
+///
+/// shapes added to the painter are drawn every frame until they're removed,
+/// which is useful for static decorations that never change
+#[derive(Debug, Default)]
+pub struct Painter {
+    meshes: IndexMap<MeshHandle, (Mesh, f32)>,
+    next_handle: u64,
+    dirty: bool,
+}
+
+impl Painter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a shape in an absolute position, it will be drawn
+    /// every frame until it's removed with [`Painter::remove_mesh`]
+    ///
+    /// shapes with a higher `z` are drawn over the ones with a lower `z`,
+    /// see [`State::draw_shape_absolute_with_z`]
+    pub fn add_shape_absolute(&mut self, shape: Shape, z: f32) -> MeshHandle {
+        let handle = MeshHandle(self.next_handle);
+        self.next_handle += 1;
+
+        self.meshes.insert(handle, (State::create_mesh(shape), z));
+        self.dirty = true;
+
+        handle
+    }
+
+    /// replaces the mesh of an existing handle with the given shape,
+    /// keeping its z order
+    ///
+    /// returns `false` if the handle was already removed
+    pub fn update_shape_absolute(&mut self, handle: MeshHandle, shape: Shape) -> bool {
+        let Some((mesh, _)) = self.meshes.get_mut(&handle) else {
+            return false;
+        };
+
+        *mesh = State::create_mesh(shape);
+        self.dirty = true;
+
+        true
+    }
+
+    pub fn remove_mesh(&mut self, handle: MeshHandle) -> Option<Mesh> {
+        // shift to keep the draw order of the remaining meshes
+        let (mesh, _) = self.meshes.shift_remove(&handle)?;
+        self.dirty = true;
+
+        Some(mesh)
+    }
+
+    pub fn clear(&mut self) {
+        if !self.meshes.is_empty() {
+            self.meshes.clear();
+            self.dirty = true;
+        }
+    }
+
+    /// the retained meshes and their z order, in the order they were added
+    pub fn meshes(&self) -> impl Iterator<Item = (&Mesh, f32)> {
+        self.meshes.values().map(|(mesh, z)| (mesh, *z))
+    }
+
+    /// returns whether the meshes changed since the last call
+    pub(crate) fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+}
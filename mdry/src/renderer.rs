@@ -1,12 +1,13 @@
 use glyphon::{
-    Attrs, FontSystem, Metrics, Resolution, Shaping, Stretch, Style, SwashCache, TextArea,
-    TextBounds, Weight,
+    Attrs, AttrsList, FontSystem, Metrics, Resolution, SwashCache, TextArea, TextBounds, Wrap,
 };
+// so fonts and text can be set up without depending on glyphon directly
+pub use glyphon::{Shaping, Stretch, Style, Weight};
 use wgpu::util::DeviceExt;
 
 use crate::color::Color;
-use crate::shapes::Mesh;
-use crate::VertexColored;
+use crate::shapes::{ClipRect, Mesh};
+use crate::{VertexColored, VertexTextured};
 use std::hash::{Hash, Hasher};
 use std::num::NonZeroU64;
 use std::ops::Range;
@@ -14,6 +15,8 @@ use std::sync::Arc;
 
 const SCALE_FACTOR: Option<&str> = option_env!("SCALE_FACTOR");
 
+const QUAD_INDICES: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
 #[derive(Debug)]
 struct SlicedBuffer {
     buffer: wgpu::Buffer,
@@ -24,12 +27,49 @@ struct SlicedBuffer {
 #[derive(Debug)]
 pub struct Renderer {
     pipeline: wgpu::RenderPipeline,
+    /// sliced into batches of neighbouring meshes that share a clip rect,
+    /// which are drawn with a single draw call each
     index_buffer: SlicedBuffer,
+    /// sliced per mesh
     vertex_buffer: SlicedBuffer,
+    /// the clip rect of each batch
+    clips: Vec<Option<ClipRect>>,
+    /// the size of the render target in pixels
+    target_size: (u32, u32),
     uniform_buffer: wgpu::Buffer,
     scale_factor: f32,
+    srgb_target: bool,
     uniform_bind_group: wgpu::BindGroup,
     texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_pipeline: wgpu::RenderPipeline,
+    texture_sampler: wgpu::Sampler,
+    /// indices of a single quad, shared by all textured quads
+    quad_index_buffer: wgpu::Buffer,
+    /// 4 vertices per textured quad
+    textured_vertex_buffer: Option<wgpu::Buffer>,
+    textured_quads: Vec<(Arc<GpuTexture>, Option<ClipRect>)>,
+    /// how many of the first textured quads are drawn under the meshes
+    background_quads: usize,
+    /// how many times [`Renderer::update_buffers`] was called
+    buffer_updates: u64,
+}
+
+/// A texture uploaded to the GPU, ready to be drawn as a textured quad
+#[derive(Debug)]
+pub struct GpuTexture {
+    pub texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// A textured quad to be drawn in an absolute position
+#[derive(Debug, Clone)]
+pub struct TexturedQuad {
+    pub texture: Arc<GpuTexture>,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub clip: Option<ClipRect>,
 }
 
 /// Uniform buffer used when rendering.
@@ -37,13 +77,46 @@ pub struct Renderer {
 #[repr(C)]
 struct UniformBuffer {
     screen_size_in_points: [f32; 2],
+    /// 1 if the target is an sRGB format, which encodes what the shaders output,
+    /// so the shaders have to decode the sRGB colors they're given first
+    srgb_target: u32,
     // Uniform buffers need to be at least 16 bytes in WebGL.
     // See https://github.com/gfx-rs/wgpu/issues/2072
-    _padding: [u32; 2],
+    _padding: u32,
 }
 
+/// [`wgpu::BlendState::REPLACE`] for straight alpha colors drawn into a premultiplied target
+const PREMULTIPLY_REPLACE: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::Zero,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent::REPLACE,
+};
+
+/// [`wgpu::BlendState::ALPHA_BLENDING`] for straight alpha colors drawn into a premultiplied
+/// target, which keeps the target's alpha as coverage instead of squaring the source alpha
+const PREMULTIPLY_ALPHA_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent::OVER,
+};
+
 impl Renderer {
-    pub async fn new<'a>(output_color_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    /// `alpha_mode` is how the output is composited, the pipelines write
+    /// premultiplied colors when it's [`wgpu::CompositeAlphaMode::PreMultiplied`]
+    pub async fn new<'a>(
+        output_color_format: wgpu::TextureFormat,
+        alpha_mode: wgpu::CompositeAlphaMode,
+        sample_count: u32,
+        device: &wgpu::Device,
+    ) -> Self {
+        let premultiplied = alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied;
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
@@ -53,6 +126,7 @@ impl Renderer {
             label: Some("Uniform Buffer"),
             contents: bytemuck::cast_slice(&[UniformBuffer {
                 screen_size_in_points: [0.0, 0.0],
+                srgb_target: output_color_format.is_srgb() as u32,
                 _padding: Default::default(),
             }]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
@@ -63,7 +137,8 @@ impl Renderer {
                 label: Some("Uniform Bind Group Layout"),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    // the fragment shaders read `srgb_target`
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         has_dynamic_offset: false,
                         min_binding_size: NonZeroU64::new(std::mem::size_of::<UniformBuffer>() as _),
@@ -129,7 +204,11 @@ impl Renderer {
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
                     format: output_color_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    blend: Some(if premultiplied {
+                        PREMULTIPLY_REPLACE
+                    } else {
+                        wgpu::BlendState::REPLACE
+                    }),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
@@ -147,13 +226,82 @@ impl Renderer {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
         });
 
+        let texture_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Texture Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("texture.wgsl").into()),
+        });
+
+        let texture_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Texture Render Pipeline Layout"),
+                bind_group_layouts: &[&uniform_bind_group_layout, &texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let texture_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Texture Render Pipeline"),
+            layout: Some(&texture_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &texture_shader,
+                entry_point: "vs_main",
+                buffers: &[VertexTextured::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &texture_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: output_color_format,
+                    // icons usually have transparent parts
+                    blend: Some(if premultiplied {
+                        PREMULTIPLY_ALPHA_BLENDING
+                    } else {
+                        wgpu::BlendState::ALPHA_BLENDING
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let quad_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Quad Index Buffer"),
+            contents: bytemuck::cast_slice(&QUAD_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
         const VERTEX_BUFFER_START_CAPACITY: wgpu::BufferAddress =
             (std::mem::size_of::<VertexColored>() * 1024) as _;
         const INDEX_BUFFER_START_CAPACITY: wgpu::BufferAddress =
@@ -185,15 +333,94 @@ impl Renderer {
                 slices: Vec::with_capacity(64),
                 capacity: VERTEX_BUFFER_START_CAPACITY,
             },
+            clips: Vec::with_capacity(64),
+            target_size: (0, 0),
+            srgb_target: output_color_format.is_srgb(),
             scale_factor: SCALE_FACTOR
                 .map(|s| s.parse::<f32>().unwrap_or(1.0))
                 .unwrap_or(1.0),
             uniform_buffer,
             uniform_bind_group,
             texture_bind_group_layout,
+            texture_pipeline,
+            texture_sampler,
+            quad_index_buffer,
+            textured_vertex_buffer: None,
+            textured_quads: Vec::new(),
+            background_quads: 0,
+            buffer_updates: 0,
         }
     }
 
+    /// Upload an image so it can be drawn as a textured quad
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::RgbaImage,
+    ) -> Option<GpuTexture> {
+        // wgpu panics on textures without any pixels
+        if image.width() == 0 || image.height() == 0 {
+            return None;
+        }
+
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // not sRGB, the pixels are written as is like the mesh colors
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * image.width()),
+                rows_per_image: Some(image.height()),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Bind Group"),
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.texture_sampler),
+                },
+            ],
+        });
+
+        Some(GpuTexture {
+            texture,
+            bind_group,
+        })
+    }
+
     // pub fn resize(&mut self, width: u32, height: u32) {
     //     if width > 0 && height > 0 {
     //         self.width = width;
@@ -208,49 +435,156 @@ impl Renderer {
 
     /// Render/draw the provided meshes
     pub fn render<'rp>(&'rp self, render_pass: &mut wgpu::RenderPass<'rp>) {
-        let index_buffer_slices = self.index_buffer.slices.iter();
-        let vertex_buffer_slices = self.vertex_buffer.slices.iter();
-        for (index_buffer_slice, vertex_buffer_slice) in
-            index_buffer_slices.zip(vertex_buffer_slices)
-        {
+        self.render_textured(render_pass, 0..self.background_quads);
+
+        if let (Some(indices), Some(vertices)) = (
+            self.index_buffer.slices.last(),
+            self.vertex_buffer.slices.last(),
+        ) {
+            // every mesh shares the pipeline and buffers, only the scissor rect
+            // changes between batches
             render_pass.set_pipeline(&self.pipeline);
             render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-
             render_pass.set_index_buffer(
-                self.index_buffer
-                    .buffer
-                    .slice(index_buffer_slice.start as u64..index_buffer_slice.end as u64),
+                self.index_buffer.buffer.slice(..indices.end as u64),
                 wgpu::IndexFormat::Uint32,
             );
-            render_pass.set_vertex_buffer(
-                0,
-                self.vertex_buffer
-                    .buffer
-                    .slice(vertex_buffer_slice.start as u64..vertex_buffer_slice.end as u64),
-            );
+            render_pass
+                .set_vertex_buffer(0, self.vertex_buffer.buffer.slice(..vertices.end as u64));
 
-            let len = (index_buffer_slice.len() / std::mem::size_of::<u32>()) - 1;
+            for (batch, clip) in self.index_buffer.slices.iter().zip(self.clips.iter()) {
+                let Some((x, y, width, height)) = self.scissor_rect(clip) else {
+                    // clipped out entirely
+                    continue;
+                };
+                render_pass.set_scissor_rect(x, y, width, height);
 
-            render_pass.draw_indexed(0..len as u32 + 1, 0, 0..1);
+                let index_size = std::mem::size_of::<u32>();
+                let indices = (batch.start / index_size) as u32..(batch.end / index_size) as u32;
+                render_pass.draw_indexed(indices, 0, 0..1);
+            }
         }
+
+        // the rest of the textured quads are drawn over the meshes
+        self.render_textured(
+            render_pass,
+            self.background_quads..self.textured_quads.len(),
+        );
     }
 
-    // pub fn update_textures(&mut self, queue: &wgpu::Queue, window_width: u32, window_height: u32) {}
+    fn render_textured<'rp>(
+        &'rp self,
+        render_pass: &mut wgpu::RenderPass<'rp>,
+        quads: std::ops::Range<usize>,
+    ) {
+        let Some(textured_vertex_buffer) = &self.textured_vertex_buffer else {
+            return;
+        };
 
-    pub fn update_buffers(
+        if quads.is_empty() {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.texture_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_vertex_buffer(0, textured_vertex_buffer.slice(..));
+
+        for i in quads {
+            let (texture, clip) = &self.textured_quads[i];
+            let Some((x, y, width, height)) = self.scissor_rect(clip) else {
+                continue;
+            };
+            render_pass.set_scissor_rect(x, y, width, height);
+
+            render_pass.set_bind_group(1, &texture.bind_group, &[]);
+            render_pass.draw_indexed(0..QUAD_INDICES.len() as u32, i as i32 * 4, 0..1);
+        }
+    }
+
+    /// converts a clip rect to a scissor rect in pixels clamped to the render target,
+    /// returns `None` when nothing would be visible
+    fn scissor_rect(&self, clip: &Option<ClipRect>) -> Option<(u32, u32, u32, u32)> {
+        let (target_width, target_height) = self.target_size;
+        let Some(clip) = clip else {
+            return Some((0, 0, target_width, target_height));
+        };
+
+        let left = (clip.x.floor().max(0.) as u32).min(target_width);
+        let top = (clip.y.floor().max(0.) as u32).min(target_height);
+        let right = ((clip.x + clip.width).ceil().max(0.) as u32).min(target_width);
+        let bottom = ((clip.y + clip.height).ceil().max(0.) as u32).min(target_height);
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some((left, top, right - left, bottom - top))
+    }
+
+    /// Upload the textured quads to draw, replacing the previous ones,
+    /// the first `background_quads` are drawn under the meshes and the rest over them
+    pub fn update_textures(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        _encoder: &mut wgpu::CommandEncoder,
-        meshes: Vec<Mesh>,
-        window_width: u32,
-        window_height: u32,
+        quads: &[TexturedQuad],
+        background_quads: usize,
     ) {
-        let (vertex_count, index_count) = {
-            meshes.iter().fold((0, 0), |acc, mesh| {
-                (acc.0 + mesh.vertices.len(), acc.1 + mesh.indices.len())
+        self.textured_quads.clear();
+        self.background_quads = background_quads.min(quads.len());
+
+        // the buffer is kept for the next quads
+        if quads.is_empty() {
+            return;
+        }
+
+        let vertices: Vec<VertexTextured> = quads
+            .iter()
+            .flat_map(|quad| {
+                let (left, top) = (quad.x, quad.y);
+                let (right, bottom) = (quad.x + quad.width, quad.y + quad.height);
+                [
+                    VertexTextured {
+                        position: [left, top, 0.],
+                        uv: [0., 0.],
+                    },
+                    VertexTextured {
+                        position: [left, bottom, 0.],
+                        uv: [0., 1.],
+                    },
+                    VertexTextured {
+                        position: [right, bottom, 0.],
+                        uv: [1., 1.],
+                    },
+                    VertexTextured {
+                        position: [right, top, 0.],
+                        uv: [1., 0.],
+                    },
+                ]
             })
+            .collect();
+
+        let contents: &[u8] = bytemuck::cast_slice(&vertices);
+        let buffer = match self.textured_vertex_buffer.take() {
+            Some(buffer) if buffer.size() >= contents.len() as wgpu::BufferAddress => buffer,
+            _ => device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Textured Vertex Buffer"),
+                size: contents.len() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
         };
+        queue.write_buffer(&buffer, 0, contents);
+        self.textured_vertex_buffer = Some(buffer);
+
+        self.textured_quads
+            .extend(quads.iter().map(|quad| (quad.texture.clone(), quad.clip)));
+    }
+
+    /// Upload the screen size, this only needs to happen when the window is resized
+    pub fn update_uniforms(&mut self, queue: &wgpu::Queue, window_width: u32, window_height: u32) {
+        self.target_size = (window_width, window_height);
 
         queue.write_buffer(
             &self.uniform_buffer,
@@ -260,12 +594,49 @@ impl Renderer {
                     window_width as f32 / self.scale_factor,
                     window_height as f32 / self.scale_factor,
                 ],
+                srgb_target: self.srgb_target as u32,
                 _padding: Default::default(),
             }]),
         );
+    }
+
+    /// how many times the meshes were uploaded with [`Renderer::update_buffers`],
+    /// to check that unchanged frames skip it
+    pub fn buffer_updates(&self) -> u64 {
+        self.buffer_updates
+    }
+
+    /// Upload the provided meshes, replacing the previously uploaded ones
+    ///
+    /// meshes are drawn in ascending z order, meshes with the same z
+    /// are drawn in the order they're provided in, each mesh is clipped
+    /// to its clip rect if it has one
+    ///
+    /// the uploaded slices are kept until the next call, so this only
+    /// needs to be called when the meshes change
+    pub fn update_buffers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mut meshes: Vec<(&Mesh, f32, Option<ClipRect>)>,
+    ) {
+        self.buffer_updates += 1;
+
+        // stable sort so ties keep their insertion order
+        meshes.sort_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+
+        self.clips.clear();
+
+        let (vertex_count, index_count) = {
+            meshes.iter().fold((0, 0), |acc, (mesh, _, _)| {
+                (acc.0 + mesh.vertices.len(), acc.1 + mesh.indices.len())
+            })
+        };
+
+        self.index_buffer.slices.clear();
+        self.vertex_buffer.slices.clear();
 
         if index_count > 0 {
-            self.index_buffer.slices.clear();
             let required_index_buffer_size = (std::mem::size_of::<u32>() * index_count) as u64;
 
             if self.index_buffer.capacity < required_index_buffer_size {
@@ -288,18 +659,34 @@ impl Renderer {
                 )
                 .expect("Failed to create staging buffer for index data");
             let mut index_offset = 0;
-            for mesh in &meshes {
+            // the first vertex of each mesh in the vertex buffer
+            let mut base_vertex = 0;
+            for (mesh, _, clip) in &meshes {
                 let size = mesh.indices.len() * std::mem::size_of::<u32>();
                 let slice = index_offset..(size + index_offset);
-                index_buffer_staging[slice.clone()]
-                    .copy_from_slice(bytemuck::cast_slice(&mesh.indices));
-                self.index_buffer.slices.push(slice);
+                // indices point into the whole vertex buffer, so a batch of
+                // meshes can be drawn with one call
+                for (bytes, index) in index_buffer_staging[slice.clone()]
+                    .chunks_exact_mut(std::mem::size_of::<u32>())
+                    .zip(&mesh.indices)
+                {
+                    bytes.copy_from_slice(bytemuck::bytes_of(&(index + base_vertex)));
+                }
+
+                match self.index_buffer.slices.last_mut() {
+                    Some(batch) if self.clips.last() == Some(clip) => batch.end = slice.end,
+                    _ => {
+                        self.index_buffer.slices.push(slice);
+                        self.clips.push(*clip);
+                    }
+                }
+
                 index_offset += size;
+                base_vertex += mesh.vertices.len() as u32;
             }
         }
 
         if vertex_count > 0 {
-            self.vertex_buffer.slices.clear();
             let required_vertex_buffer_size =
                 (std::mem::size_of::<VertexColored>() * vertex_count) as u64;
             if self.vertex_buffer.capacity < required_vertex_buffer_size {
@@ -322,7 +709,7 @@ impl Renderer {
                 )
                 .expect("Failed to create staging buffer for vertex data");
             let mut vertex_offset = 0;
-            for mesh in meshes {
+            for (mesh, _, _) in &meshes {
                 let size = mesh.vertices.len() * std::mem::size_of::<VertexColored>();
                 let slice = vertex_offset..(size + vertex_offset);
                 vertex_buffer_staging[slice.clone()]
@@ -342,10 +729,26 @@ pub struct TextRenderer {
 }
 
 pub enum TextTypes {
-    Managed { text: ManagedText },
+    Managed {
+        text: ManagedText,
+        clip: Option<ClipRect>,
+        /// applied when the text is drawn, since the text itself is shared
+        translation: (f32, f32),
+    },
     Cached(CachedText),
+    /// a text owned by the state, see [`State::create_text`](crate::State::create_text)
+    Owned {
+        handle: TextHandle,
+        clip: Option<ClipRect>,
+        translation: (f32, f32),
+    },
 }
 
+/// A handle to a text owned by the [`State`](crate::State), see
+/// [`State::create_text`](crate::State::create_text)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextHandle(pub(crate) u64);
+
 #[derive(Debug)]
 pub struct ManagedText {
     pub(crate) raw: std::sync::Weak<TextInner>,
@@ -361,6 +764,7 @@ impl ManagedText {
 pub struct TextInner {
     pub x: f32,
     pub y: f32,
+    /// applied when the text is drawn, so changing it doesn't need the buffer to be reshaped
     pub color: Color,
     pub content: String,
     pub bounds: TextBounds,
@@ -383,16 +787,93 @@ impl TextInner {
         let mut buffer = glyphon::Buffer::new(font_system, Metrics::new(font_size, font_size));
         buffer.set_size(font_system, initial_width, initial_height);
 
-        buffer.set_text(
-            font_system,
-            content,
-            Attrs::new().family(font.family.into_glyphon_family()),
-            Shaping::Advanced,
+        buffer.set_text(font_system, content, font.attrs(), Shaping::Advanced);
+
+        let (width, height) = fit_to_text(&mut buffer, font_system);
+
+        Self {
+            x,
+            y,
+            color,
+            content: content.to_string(),
+            bounds: TextBounds {
+                left: x as i32,
+                top: y as i32,
+                right: (x + width) as i32,
+                bottom: (y + height) as i32,
+            },
+            buffer,
+            font,
+        }
+    }
+
+    /// replaces the text of a [`TextInner::new`] text, returns whether it changed
+    ///
+    /// unchanged text is skipped, and a single line is replaced in place instead of
+    /// rebuilding the buffer's lines like `set_text` does, so texts updated every second,
+    /// like clocks, only pay for shaping the new line
+    pub fn set_content(&mut self, font_system: &mut FontSystem, content: &str) -> bool {
+        if content == self.content {
+            return false;
+        }
+
+        self.content.clear();
+        self.content.push_str(content);
+
+        // unbounded, so a wider text doesn't wrap at the old width before it's measured
+        self.buffer.set_size(font_system, f32::MAX, f32::MAX);
+        match self.buffer.lines.as_mut_slice() {
+            [line] if !content.contains('\n') => {
+                line.set_text(content, AttrsList::new(self.font.attrs()));
+            }
+            _ => self
+                .buffer
+                .set_text(font_system, content, self.font.attrs(), Shaping::Advanced),
+        }
+        self.buffer.shape_until_scroll(font_system);
+
+        let (width, height) = fit_to_text(&mut self.buffer, font_system);
+        self.bounds.right = (self.x + width) as i32;
+        self.bounds.bottom = (self.y + height) as i32;
+
+        true
+    }
+
+    /// moves the text without reshaping it
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        let (width, height) = (
+            self.bounds.right - self.bounds.left,
+            self.bounds.bottom - self.bounds.top,
         );
+        self.x = x;
+        self.y = y;
+        self.bounds.left = x as i32;
+        self.bounds.top = y as i32;
+        self.bounds.right = x as i32 + width;
+        self.bounds.bottom = y as i32 + height;
+    }
+
+    /// like [`TextInner::new`], but lines longer than `wrap_width` wrap at word boundaries,
+    /// the height of the wrapped text is in `bounds`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_wrapped(
+        font_system: &mut FontSystem,
+        content: &str,
+        x: f32,
+        y: f32,
+        font_size: f32,
+        color: Color,
+        font: Font,
+        wrap_width: f32,
+    ) -> Self {
+        let mut buffer = glyphon::Buffer::new(font_system, Metrics::new(font_size, font_size));
+        buffer.set_wrap(font_system, Wrap::Word);
+        // unbounded height, so no wrapped line is cut off before it's measured
+        buffer.set_size(font_system, wrap_width, f32::MAX);
 
-        let (width, height) = measure_text(&buffer);
+        buffer.set_text(font_system, content, font.attrs(), Shaping::Advanced);
 
-        buffer.set_size(font_system, width, height);
+        let (width, height) = fit_to_text(&mut buffer, font_system);
 
         Self {
             x,
@@ -416,10 +897,16 @@ pub struct CachedText {
     pub y: f32,
     pub content: String,
     pub bounds: TextBounds,
+    /// applied when the text is drawn, it isn't part of the cache key
     pub color: Color,
     pub font_size: f32,
     pub line_height: f32,
     pub font: Font,
+    /// families to fall back to, in order, for glyphs missing from `font`
+    pub font_stack: Option<FontStack>,
+    /// the length in bytes and color of each part of `content`, in order,
+    /// these colors are shaped into the buffer and win over `color`
+    pub colors: Option<Vec<(usize, Color)>>,
     pub shaping: Shaping,
 }
 
@@ -431,7 +918,8 @@ pub struct TextCacheKey {
     // this is u32 just for Eq
     pub line_height: u32,
     pub font: Font,
-    pub bounds: TextBounds,
+    pub font_stack: Option<FontStack>,
+    pub colors: Option<Vec<(usize, Color)>>,
     pub shaping: Shaping,
 }
 
@@ -441,10 +929,8 @@ impl<'a> Hash for TextCacheKey {
         self.font_size.hash(&mut hasher);
         self.line_height.hash(&mut hasher);
         self.font.hash(&mut hasher);
-        self.bounds.left.hash(&mut hasher);
-        self.bounds.top.hash(&mut hasher);
-        self.bounds.right.hash(&mut hasher);
-        self.bounds.bottom.hash(&mut hasher);
+        self.font_stack.hash(&mut hasher);
+        self.colors.hash(&mut hasher);
         self.shaping.hash(&mut hasher);
     }
 }
@@ -487,6 +973,117 @@ impl Font {
             ..Self::DEFAULT
         }
     }
+
+    /// Returns this [`Font`] with [`Weight::BOLD`].
+    pub const fn bold(self) -> Self {
+        self.weight(Weight::BOLD)
+    }
+
+    /// Returns this [`Font`] with [`Style::Italic`].
+    pub const fn italic(self) -> Self {
+        Font {
+            style: Style::Italic,
+            ..self
+        }
+    }
+
+    /// Returns this [`Font`] with the given [`Weight`].
+    pub const fn weight(self, weight: Weight) -> Self {
+        Font { weight, ..self }
+    }
+
+    /// Returns this [`Font`] with the given [`Stretch`].
+    pub const fn stretch(self, stretch: Stretch) -> Self {
+        Font { stretch, ..self }
+    }
+
+    /// The glyphon [`Attrs`] to shape text with this [`Font`].
+    ///
+    /// they never carry a color, texts are colored when they're drawn so the color
+    /// can change without shaping them again, a color set in the attrs would win over it
+    pub fn attrs(&self) -> Attrs<'static> {
+        Attrs::new()
+            .family(self.family.into_glyphon_family())
+            .weight(self.weight)
+            .stretch(self.stretch)
+            .style(self.style)
+    }
+}
+
+/// An ordered list of font families, glyphs missing from one family
+/// are taken from the next family that has them.
+///
+/// useful for labels that mix an icon font with a text font, like " 87%"
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FontStack(pub Vec<Family>);
+
+impl FontStack {
+    /// Splits `content` into spans, each one shaped with the first family in the stack
+    /// that has its glyphs, the rest of `attrs` is kept as is.
+    ///
+    /// characters no family has are left to cosmic-text's own fallback
+    pub fn spans<'s>(
+        &self,
+        font_system: &mut FontSystem,
+        content: &'s str,
+        attrs: Attrs<'static>,
+    ) -> Vec<(&'s str, Attrs<'static>)> {
+        let mut spans: Vec<(&'s str, Attrs<'static>)> = Vec::new();
+        let mut start = 0;
+        let mut current: Option<Family> = None;
+
+        for (i, c) in content.char_indices() {
+            // whitespace is in every font, keep it in the current span
+            if c.is_whitespace() && current.is_some() {
+                continue;
+            }
+
+            let family = self
+                .0
+                .iter()
+                .copied()
+                .find(|family| has_glyph(font_system, *family, attrs, c))
+                .or_else(|| self.0.first().copied());
+
+            if family != current {
+                if let Some(current) = current {
+                    spans.push((
+                        &content[start..i],
+                        attrs.family(current.into_glyphon_family()),
+                    ));
+                }
+                start = i;
+                current = family;
+            }
+        }
+
+        match current {
+            Some(current) => spans.push((
+                &content[start..],
+                attrs.family(current.into_glyphon_family()),
+            )),
+            None => spans.push((content, attrs)),
+        }
+
+        spans
+    }
+}
+
+/// whether the font `family` resolves to with `attrs` has a glyph for `c`
+fn has_glyph(font_system: &mut FontSystem, family: Family, attrs: Attrs, c: char) -> bool {
+    let families = [family.into_glyphon_family()];
+    let query = glyphon::fontdb::Query {
+        families: &families,
+        weight: attrs.weight,
+        stretch: attrs.stretch,
+        style: attrs.style,
+    };
+
+    font_system
+        .db()
+        .query(&query)
+        .and_then(|id| font_system.get_font(id))
+        .is_some_and(|font| font.as_swash().charmap().map(c) != 0)
 }
 
 /// A font family.
@@ -532,13 +1129,13 @@ impl Family {
 }
 
 impl TextRenderer {
-    pub fn prepare(
+    pub fn prepare<'a>(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         width: u32,
         height: u32,
-        texts: Vec<TextArea>,
+        texts: impl IntoIterator<Item = TextArea<'a>>,
     ) -> Result<(), wgpu::SurfaceError> {
         self.renderer
             .prepare(
@@ -568,12 +1165,174 @@ impl TextRenderer {
     }
 }
 
+/// Lines drawn over text, see [`State::draw_text_absolute_cached_decorated`](crate::State::draw_text_absolute_cached_decorated)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextDecoration {
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl TextDecoration {
+    pub const NONE: Self = Self {
+        underline: false,
+        strikethrough: false,
+    };
+
+    pub const UNDERLINE: Self = Self {
+        underline: true,
+        strikethrough: false,
+    };
+
+    pub const STRIKETHROUGH: Self = Self {
+        underline: false,
+        strikethrough: true,
+    };
+}
+
+/// the height the buffer needs for all of its lines to be laid out,
+/// a buffer shorter than its line height doesn't lay out any line,
+/// so buffers shouldn't be sized with the height [`measure_text`] returns
+pub fn layout_height(buffer: &glyphon::Buffer) -> f32 {
+    buffer.layout_runs().len() as f32 * buffer.metrics().line_height
+}
+
+/// the size of a laid out text, and where its first line sits
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextMetrics {
+    /// the width of the widest line
+    pub width: f32,
+    /// from the top of the buffer to the lowest descent of the last line
+    pub height: f32,
+    /// how far the glyphs of the first line reach above the baseline
+    pub ascent: f32,
+    /// how far the glyphs of the first line reach below the baseline
+    pub descent: f32,
+    /// the y of the first line's baseline, from the top of the buffer
+    pub baseline: f32,
+}
+
+/// sizes the buffer to its text, and returns the size [`measure_text`] gives for it
+///
+/// RTL lines are aligned to the right edge of the buffer, so it's shrunk to the widest
+/// line for RTL text to start at the buffer's left edge like LTR text, the width is
+/// rounded up so the text doesn't wrap again at exactly its own width
+pub fn fit_to_text(buffer: &mut glyphon::Buffer, font_system: &mut FontSystem) -> (f32, f32) {
+    let (width, height) = measure_text(buffer);
+    buffer.set_size(font_system, width.ceil(), layout_height(buffer));
+    (width, height)
+}
+
+/// returns the width of the widest line, and the height from the top
+/// of the buffer to the lowest descent of the last line
 pub fn measure_text(buffer: &glyphon::Buffer) -> (f32, f32) {
-    let (width, total_lines) = buffer
-        .layout_runs()
-        .fold((0.0, 0usize), |(width, total_lines), run| {
-            (run.line_w.max(width), total_lines + 1)
-        });
+    let metrics = measure_text_detailed(buffer);
+    (metrics.width, metrics.height)
+}
 
-    (width, total_lines as f32 * buffer.metrics().line_height)
+/// same as [`measure_text`], with the ascent, descent and baseline of the first line
+pub fn measure_text_detailed(buffer: &glyphon::Buffer) -> TextMetrics {
+    let mut metrics = TextMetrics::default();
+    // a buffer line can wrap into several layout lines (and runs)
+    let mut layout_line = (usize::MAX, 0);
+
+    for (i, run) in buffer.layout_runs().enumerate() {
+        metrics.width = metrics.width.max(run.line_w);
+
+        layout_line = if layout_line.0 == run.line_i {
+            (run.line_i, layout_line.1 + 1)
+        } else {
+            (run.line_i, 0)
+        };
+
+        let (ascent, descent) = buffer.lines[run.line_i]
+            .layout_opt()
+            .as_ref()
+            .and_then(|lines| lines.get(layout_line.1))
+            .map_or((0., 0.), |line| (line.max_ascent, line.max_descent));
+
+        if i == 0 {
+            metrics.ascent = ascent;
+            metrics.descent = descent;
+            metrics.baseline = run.line_y;
+        }
+
+        metrics.height = metrics.height.max(run.line_y + descent);
+    }
+
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RTL: &str = "مرحبا بالعالم";
+
+    /// where the first glyph starts, and how many lines the text was laid out in
+    fn start_and_lines(buffer: &glyphon::Buffer) -> (f32, usize) {
+        let start = buffer
+            .layout_runs()
+            .flat_map(|run| run.glyphs.iter())
+            .map(|glyph| glyph.x)
+            .fold(f32::MAX, f32::min);
+        (start, buffer.layout_runs().count())
+    }
+
+    fn text(font_system: &mut FontSystem, content: &str) -> TextInner {
+        TextInner::new(
+            font_system,
+            content,
+            0.,
+            0.,
+            1000.,
+            30.,
+            14.,
+            Color::rgb(255, 255, 255),
+            Font::DEFAULT,
+        )
+    }
+
+    #[test]
+    fn rtl_text_starts_at_x() {
+        let mut font_system = FontSystem::new();
+
+        for content in [RTL, "abc مرحبا", "مرحبا abc"] {
+            let (start, lines) = start_and_lines(&text(&mut font_system, content).buffer);
+            assert!(start.abs() < 1., "`{content}` starts at {start}");
+            assert_eq!(lines, 1, "`{content}` wrapped");
+        }
+    }
+
+    #[test]
+    fn rtl_text_starts_at_x_after_set_content() {
+        let mut font_system = FontSystem::new();
+
+        let mut text = text(&mut font_system, "abc");
+        text.set_content(&mut font_system, RTL);
+
+        let (start, lines) = start_and_lines(&text.buffer);
+        assert!(start.abs() < 1., "starts at {start}");
+        assert_eq!(lines, 1);
+    }
+
+    #[test]
+    fn wrapped_rtl_text_starts_at_x() {
+        let mut font_system = FontSystem::new();
+
+        let text = TextInner::new_wrapped(
+            &mut font_system,
+            RTL,
+            0.,
+            0.,
+            14.,
+            Color::rgb(255, 255, 255),
+            Font::DEFAULT,
+            1000.,
+        );
+
+        let (start, lines) = start_and_lines(&text.buffer);
+        assert!(start.abs() < 1., "starts at {start}");
+        assert_eq!(lines, 1);
+        assert!(text.bounds.right < 1000);
+    }
 }
@@ -10,9 +10,11 @@ struct VertexOutput {
 
 struct UniformBuffer {
     screen_size: vec2<f32>,
+    // 1 if the target encodes the output to sRGB itself
+    srgb_target: u32,
     // Uniform buffers need to be at least 16 bytes in WebGL.
     // See https://github.com/gfx-rs/wgpu/issues/2072
-    _padding: vec2<u32>,
+    _padding: u32,
 };
 
 @group(0) @binding(0) var<uniform> uniform_buffer: UniformBuffer;
@@ -44,7 +46,19 @@ fn gamma_from_linear_rgb(rgb: vec3<f32>) -> vec3<f32> {
     return select(higher, lower, cutoff);
 }
 
+// 0-1 linear  from  0-1 sRGB gamma
+fn linear_from_gamma_rgb(rgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = rgb < vec3<f32>(0.04045);
+    let lower = rgb / vec3<f32>(12.92);
+    let higher = pow((rgb + vec3<f32>(0.055)) / vec3<f32>(1.055), vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // colors are given in sRGB, which a linear target stores as is
+    if uniform_buffer.srgb_target != 0u {
+        return vec4<f32>(linear_from_gamma_rgb(in.color.rgb), 1.0);
+    }
     return vec4<f32>(in.color.rgb, 1.0);
 }
@@ -1,11 +1,75 @@
 use crate::VertexColored;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Mesh {
     pub indices: Vec<u32>,
     pub vertices: Vec<VertexColored>,
 }
 
+impl Mesh {
+    /// moves every vertex by `dx` and `dy` pixels, to reuse a mesh somewhere else
+    /// without building it again
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        for vertex in self.vertices.iter_mut() {
+            vertex.translate(dx, dy);
+        }
+    }
+
+    /// the area under a line through `samples`, spread evenly over the width of `area`
+    /// and filled with its color, samples go from 0 at the bottom to 1 at the top
+    /// and are clamped to that
+    pub fn sparkline(samples: &[f32], area: &Rect) -> Mesh {
+        let color = area.color.rgb_f32();
+        let bottom = area.y + area.height as f32;
+        let top = |sample: f32| bottom - sample.clamp(0., 1.) * area.height as f32;
+
+        // a single sample is stretched over the whole width
+        let points: Vec<(f32, f32)> = match samples {
+            [] => Vec::new(),
+            [sample] => vec![
+                (area.x, top(*sample)),
+                (area.x + area.width as f32, top(*sample)),
+            ],
+            samples => {
+                let step = area.width as f32 / (samples.len() - 1) as f32;
+                samples
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sample)| (area.x + step * i as f32, top(*sample)))
+                    .collect()
+            }
+        };
+
+        // a vertex at the bottom and one on the line for every point,
+        // with a quad between each pair of points
+        let vertices = points
+            .iter()
+            .flat_map(|(x, y)| {
+                [
+                    VertexColored {
+                        position: [*x, bottom, 0.],
+                        color,
+                    },
+                    VertexColored {
+                        position: [*x, *y, 0.],
+                        color,
+                    },
+                ]
+            })
+            .collect();
+
+        let indices = (1..points.len() as u32)
+            .flat_map(|i| {
+                let (bottom, line) = (i * 2 - 2, i * 2 - 1);
+                let (next_bottom, next_line) = (i * 2, i * 2 + 1);
+                [bottom, line, next_line, bottom, next_line, next_bottom]
+            })
+            .collect();
+
+        Mesh { indices, vertices }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Rect {
     pub x: f32,
@@ -15,6 +79,59 @@ pub struct Rect {
     pub color: crate::color::Color,
 }
 
+impl Rect {
+    /// whether the point is inside the rect, the right and bottom edges are outside
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x
+            && x < self.x + self.width as f32
+            && y >= self.y
+            && y < self.y + self.height as f32
+    }
+
+    /// whether the rects overlap, rects that only touch at an edge don't
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width as f32
+            && other.x < self.x + self.width as f32
+            && self.y < other.y + other.height as f32
+            && other.y < self.y + self.height as f32
+    }
+}
+
+/// A horizontal bar filled from the left, for levels like the battery or the volume
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    pub x: f32,
+    pub y: f32,
+    pub width: u32,
+    pub height: u32,
+    /// how much of the bar is filled, from 0 to 1
+    pub fraction: f32,
+    pub foreground: crate::color::Color,
+    pub background: crate::color::Color,
+}
+
+impl ProgressBar {
+    /// the whole bar in the background color, and the filled part in the foreground color
+    pub fn rects(&self) -> (Rect, Rect) {
+        let background = Rect {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            color: self.background,
+        };
+
+        let filled = (self.width as f32 * self.fraction.clamp(0., 1.)).round() as u32;
+        let foreground = Rect {
+            width: filled,
+            color: self.foreground,
+            ..background.clone()
+        };
+
+        (background, foreground)
+    }
+}
+
 #[derive(Debug)]
 pub struct Circle {
     pub x: f32,
@@ -37,3 +154,149 @@ pub enum Shape {
     Circle(Circle),
     Triangle(Triangle),
 }
+
+impl Shape {
+    /// the smallest rect, on whole pixels, that covers everything the shape draws,
+    /// in the shape's color
+    pub fn bounds(&self) -> Rect {
+        let (left, top, right, bottom, color) = match self {
+            Shape::Rect(rect) => return rect.clone(),
+            Shape::Circle(circle) => (
+                circle.x - circle.radius,
+                circle.y - circle.radius,
+                circle.x + circle.radius,
+                circle.y + circle.radius,
+                circle.color,
+            ),
+            Shape::Triangle(triangle) => {
+                let (a, b, c) = (triangle.a, triangle.b, triangle.c);
+                (
+                    a.0.min(b.0).min(c.0),
+                    a.1.min(b.1).min(c.1),
+                    a.0.max(b.0).max(c.0),
+                    a.1.max(b.1).max(c.1),
+                    triangle.color,
+                )
+            }
+        };
+
+        let (x, y) = (left.floor(), top.floor());
+        Rect {
+            x,
+            y,
+            width: (right.ceil() - x) as u32,
+            height: (bottom.ceil() - y) as u32,
+            color,
+        }
+    }
+}
+
+/// A clipping rectangle, anything drawn outside of it is discarded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ClipRect {
+    /// the overlapping area of both rects, empty if they don't overlap
+    pub fn intersect(&self, other: &ClipRect) -> ClipRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+
+        ClipRect {
+            x,
+            y,
+            width: (right - x).max(0.),
+            height: (bottom - y).max(0.),
+        }
+    }
+
+    /// the smallest rect that covers both rects
+    pub fn union(&self, other: &ClipRect) -> ClipRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+
+        ClipRect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        }
+    }
+
+    /// whether the point is inside the rect, the right and bottom edges are outside
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0. || self.height <= 0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    fn red() -> Color {
+        Color::rgb(255, 0, 0)
+    }
+
+    fn assert_bounds(shape: Shape, (x, y, width, height): (f32, f32, u32, u32)) {
+        let bounds = shape.bounds();
+        assert_eq!(
+            (bounds.x, bounds.y, bounds.width, bounds.height),
+            (x, y, width, height),
+            "{shape:?}"
+        );
+        assert_eq!(bounds.color, red());
+    }
+
+    #[test]
+    fn rect_bounds_are_the_rect() {
+        let rect = Rect {
+            x: 2.5,
+            y: 3.,
+            width: 10,
+            height: 4,
+            color: red(),
+        };
+        assert_bounds(Shape::Rect(rect), (2.5, 3., 10, 4));
+    }
+
+    #[test]
+    fn circle_bounds() {
+        let circle = |x, y| {
+            Shape::Circle(Circle {
+                x,
+                y,
+                radius: 5.,
+                color: red(),
+            })
+        };
+
+        assert_bounds(circle(10., 10.), (5., 5., 10, 10));
+        // a fractional center covers one more pixel on both axes
+        assert_bounds(circle(10.5, 10.25), (5., 5., 11, 11));
+    }
+
+    #[test]
+    fn triangle_bounds() {
+        let triangle = Shape::Triangle(Triangle {
+            a: (4., 1.),
+            b: (12.5, 8.),
+            c: (0.5, 6.),
+            color: red(),
+        });
+
+        assert_bounds(triangle, (0., 1., 13, 7));
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError {
+    #[error("Failed to load or save a snapshot: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Snapshot size {actual:?} doesn't match the golden image size {golden:?}, actual image written to {actual_path}")]
+    SizeMismatch {
+        actual: (u32, u32),
+        golden: (u32, u32),
+        actual_path: PathBuf,
+    },
+    #[error("{mismatched} pixels differ from the golden image by more than the tolerance, actual image written to {actual_path}")]
+    PixelMismatch {
+        mismatched: usize,
+        actual_path: PathBuf,
+    },
+}
+
+/// Compares a captured frame against a golden PNG on disk
+///
+/// pixels match if none of their channels differ by more than `tolerance`,
+/// on a mismatch the frame is written to `actual.png` next to the golden image
+///
+/// when the `MDRY_BLESS` environment variable is set, the frame is written
+/// as the new golden image instead, to add or update a snapshot on purpose
+///
+/// see [`State::capture_frame`](crate::State::capture_frame)
+pub fn compare_with_golden(
+    actual: &RgbaImage,
+    golden_path: impl AsRef<Path>,
+    tolerance: u8,
+) -> Result<(), SnapshotError> {
+    let golden_path = golden_path.as_ref();
+    if std::env::var_os("MDRY_BLESS").is_some() {
+        actual.save(golden_path)?;
+        return Ok(());
+    }
+
+    let golden = image::open(golden_path)?.into_rgba8();
+
+    let actual_path = golden_path.with_file_name("actual.png");
+
+    if actual.dimensions() != golden.dimensions() {
+        actual.save(&actual_path)?;
+        return Err(SnapshotError::SizeMismatch {
+            actual: actual.dimensions(),
+            golden: golden.dimensions(),
+            actual_path,
+        });
+    }
+
+    let mismatched = actual
+        .pixels()
+        .zip(golden.pixels())
+        .filter(|(a, g)| a.0.iter().zip(g.0).any(|(a, g)| a.abs_diff(g) > tolerance))
+        .count();
+
+    if mismatched > 0 {
+        actual.save(&actual_path)?;
+        return Err(SnapshotError::PixelMismatch {
+            mismatched,
+            actual_path,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a fresh directory for a test's golden and actual images
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mdry-snapshot-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(8, 4, |x, y| {
+            image::Rgba([x as u8 * 30, y as u8 * 60, 0, 255])
+        })
+    }
+
+    #[test]
+    fn matching_frame() {
+        let dir = test_dir("matching");
+        let golden = dir.join("golden.png");
+        gradient().save(&golden).unwrap();
+
+        compare_with_golden(&gradient(), &golden, 0).unwrap();
+        assert!(!dir.join("actual.png").exists());
+    }
+
+    #[test]
+    fn differences_within_tolerance() {
+        let dir = test_dir("tolerance");
+        let golden = dir.join("golden.png");
+        gradient().save(&golden).unwrap();
+
+        let mut actual = gradient();
+        actual.get_pixel_mut(3, 2).0[0] += 2;
+
+        compare_with_golden(&actual, &golden, 2).unwrap();
+        assert!(matches!(
+            compare_with_golden(&actual, &golden, 1),
+            Err(SnapshotError::PixelMismatch { mismatched: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn mismatch_writes_the_actual_frame() {
+        let dir = test_dir("mismatch");
+        let golden = dir.join("golden.png");
+        gradient().save(&golden).unwrap();
+
+        let actual = RgbaImage::new(4, 4);
+        let result = compare_with_golden(&actual, &golden, 0);
+
+        assert!(matches!(result, Err(SnapshotError::SizeMismatch { .. })));
+        let written = image::open(dir.join("actual.png")).unwrap().into_rgba8();
+        assert_eq!(written, actual);
+    }
+}
@@ -0,0 +1,60 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct UniformBuffer {
+    screen_size: vec2<f32>,
+    // 1 if the target encodes the output to sRGB itself
+    srgb_target: u32,
+    // Uniform buffers need to be at least 16 bytes in WebGL.
+    // See https://github.com/gfx-rs/wgpu/issues/2072
+    _padding: u32,
+};
+
+@group(0) @binding(0) var<uniform> uniform_buffer: UniformBuffer;
+
+@group(1) @binding(0) var t_texture: texture_2d<f32>;
+@group(1) @binding(1) var s_texture: sampler;
+
+fn position_from_screen(screen_pos: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>(
+        2.0 * screen_pos.x / uniform_buffer.screen_size.x - 1.0,
+        1.0 - 2.0 * screen_pos.y / uniform_buffer.screen_size.y,
+        0.0,
+        1.0,
+    );
+}
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = position_from_screen(in.position);
+    out.uv = in.uv;
+    return out;
+}
+
+// 0-1 linear  from  0-1 sRGB gamma
+fn linear_from_gamma_rgb(rgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = rgb < vec3<f32>(0.04045);
+    let lower = rgb / vec3<f32>(12.92);
+    let higher = pow((rgb + vec3<f32>(0.055)) / vec3<f32>(1.055), vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // icons are uploaded as plain Rgba8Unorm, so their texels are still sRGB encoded
+    let color = textureSample(t_texture, s_texture, in.uv);
+    if uniform_buffer.srgb_target != 0u {
+        return vec4<f32>(linear_from_gamma_rgb(color.rgb), color.a);
+    }
+    return color;
+}
@@ -1,4 +1,16 @@
-use x11rb::{connection::Connection, protocol::xproto, xcb_ffi::XCBConnection};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use x11rb::{
+    connection::Connection,
+    errors::{ReplyError, ReplyOrIdError},
+    protocol::xproto::{self, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, PropMode},
+    wrapper::ConnectionExt as _,
+    xcb_ffi::XCBConnection,
+};
 
 unsafe impl<'a> raw_window_handle::HasRawWindowHandle for Window<'a> {
     fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
@@ -30,6 +42,283 @@ pub struct Window<'a> {
     pub atoms: Atoms,
     pub display_scale: f32,
     pub window_type: WindowType,
+    /// whether the window was created with a 32-bit visual, which is required for
+    /// the alpha of [`State::clear_background`](crate::State::clear_background)
+    /// to show what's behind the window when a compositor is running
+    pub transparent: bool,
+    /// the cursors used with [`Window::set_cursor`] so far
+    pub cursors: CursorCache,
+    /// atoms outside of [`Atoms`], shared with other threads like the bar's event thread
+    pub atom_cache: Arc<AtomCache>,
+}
+
+/// A pointer shape from the X cursor font
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorKind {
+    /// the usual arrow
+    Default,
+    /// a hand, for things that can be clicked
+    Pointer,
+    /// an I-beam, for text that can be edited or selected
+    Text,
+    /// a watch, for something that's busy
+    Wait,
+}
+
+impl CursorKind {
+    /// the glyph in the X cursor font, its mask is the glyph after it
+    fn glyph(&self) -> u16 {
+        match self {
+            CursorKind::Default => 68,
+            CursorKind::Pointer => 60,
+            CursorKind::Text => 152,
+            CursorKind::Wait => 150,
+        }
+    }
+}
+
+/// The cursor font and the cursors created from it, each one is only created once
+#[derive(Debug, Default)]
+pub struct CursorCache {
+    font: RefCell<Option<xproto::Font>>,
+    cursors: RefCell<HashMap<CursorKind, xproto::Cursor>>,
+}
+
+/// Atoms interned by name and names of atoms, each one is only asked from the server once
+///
+/// for atoms that aren't known up front, like `_NET_SYSTEM_TRAY_S0` or the ones in events
+#[derive(Debug, Default)]
+pub struct AtomCache {
+    atoms: Mutex<HashMap<String, xproto::Atom>>,
+    names: Mutex<HashMap<xproto::Atom, String>>,
+}
+
+impl AtomCache {
+    /// the atom called `name`, created if it doesn't exist yet
+    pub fn intern(
+        &self,
+        connection: &XCBConnection,
+        name: &str,
+    ) -> Result<xproto::Atom, ReplyError> {
+        if let Some(atom) = self.atoms.lock().unwrap().get(name) {
+            return Ok(*atom);
+        }
+
+        let atom = connection
+            .intern_atom(false, name.as_bytes())?
+            .reply()?
+            .atom;
+        self.insert(atom, name);
+
+        Ok(atom)
+    }
+
+    /// the name of `atom`, fails if the server doesn't know it
+    pub fn name(
+        &self,
+        connection: &XCBConnection,
+        atom: xproto::Atom,
+    ) -> Result<String, ReplyError> {
+        if let Some(name) = self.names.lock().unwrap().get(&atom) {
+            return Ok(name.clone());
+        }
+
+        let reply = connection.get_atom_name(atom)?.reply()?;
+        let name = String::from_utf8_lossy(&reply.name).into_owned();
+        self.insert(atom, &name);
+
+        Ok(name)
+    }
+
+    fn insert(&self, atom: xproto::Atom, name: &str) {
+        self.atoms.lock().unwrap().insert(name.to_string(), atom);
+        self.names.lock().unwrap().insert(atom, name.to_string());
+    }
+}
+
+impl<'a> Window<'a> {
+    /// changes the pointer shape shown over the window
+    pub fn set_cursor(&self, kind: CursorKind) -> Result<(), ReplyOrIdError> {
+        let cursor = self.cursor(kind)?;
+
+        let change = ChangeWindowAttributesAux::new().cursor(cursor);
+        self.connection
+            .change_window_attributes(self.xid, &change)?
+            .check()?;
+
+        Ok(())
+    }
+
+    fn cursor(&self, kind: CursorKind) -> Result<xproto::Cursor, ReplyOrIdError> {
+        if let Some(cursor) = self.cursors.cursors.borrow().get(&kind) {
+            return Ok(*cursor);
+        }
+
+        let mut font = self.cursors.font.borrow_mut();
+        let font = match *font {
+            Some(font) => font,
+            None => {
+                let id = self.connection.generate_id()?;
+                self.connection.open_font(id, b"cursor")?;
+                *font.insert(id)
+            }
+        };
+
+        let cursor = self.connection.generate_id()?;
+        let glyph = kind.glyph();
+        self.connection.create_glyph_cursor(
+            cursor,
+            font,
+            font,
+            glyph,
+            glyph + 1,
+            0,
+            0,
+            0,
+            u16::MAX,
+            u16::MAX,
+            u16::MAX,
+        )?;
+
+        self.cursors.cursors.borrow_mut().insert(kind, cursor);
+
+        Ok(cursor)
+    }
+
+    /// sets the title shown in window lists and task switchers
+    pub fn set_title(&self, title: &str) -> Result<(), ReplyError> {
+        self.connection
+            .change_property8(
+                PropMode::REPLACE,
+                self.xid,
+                self.atoms._NET_WM_NAME,
+                self.atoms.UTF8_STRING,
+                title.as_bytes(),
+            )?
+            .check()?;
+
+        // the legacy title, for window managers that don't read _NET_WM_NAME
+        self.connection
+            .change_property8(
+                PropMode::REPLACE,
+                self.xid,
+                self.atoms.WM_NAME,
+                AtomEnum::STRING,
+                title.as_bytes(),
+            )?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// sets `WM_CLASS`, which window managers and compositors match their rules against
+    ///
+    /// `instance` is usually the program name, and `class` the capitalized program name
+    pub fn set_class(&self, instance: &str, class: &str) -> Result<(), ReplyError> {
+        // two null terminated strings back to back, see the ICCCM
+        let value = format!("{instance}\0{class}\0");
+
+        self.connection
+            .change_property8(
+                PropMode::REPLACE,
+                self.xid,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                value.as_bytes(),
+            )?
+            .check()?;
+
+        Ok(())
+    }
+}
+
+/// the name of the running window manager and the EWMH hints it supports,
+/// read from `_NET_SUPPORTING_WM_CHECK` and `_NET_SUPPORTED` on the root window
+#[derive(Debug, Clone, Default)]
+pub struct WmInfo {
+    /// `None` when no EWMH compliant window manager is running
+    pub name: Option<String>,
+    supported: Vec<xproto::Atom>,
+}
+
+impl WmInfo {
+    pub fn query(
+        connection: &XCBConnection,
+        screen_num: usize,
+        atoms: &Atoms,
+    ) -> Result<Self, ReplyError> {
+        let root = connection.setup().roots[screen_num].root;
+
+        let supported = connection
+            .get_property(
+                false,
+                root,
+                atoms._NET_SUPPORTED,
+                AtomEnum::ATOM,
+                0,
+                u32::MAX,
+            )?
+            .reply()?
+            .value32()
+            .map(|atoms| atoms.collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            name: wm_name(connection, root, atoms)?,
+            supported,
+        })
+    }
+
+    /// whether the window manager lists `atom` in `_NET_SUPPORTED`
+    pub fn supports(&self, atom: xproto::Atom) -> bool {
+        self.supported.contains(&atom)
+    }
+}
+
+/// follows `_NET_SUPPORTING_WM_CHECK` from the root to the window manager's check window
+fn wm_name(
+    connection: &XCBConnection,
+    root: xproto::Window,
+    atoms: &Atoms,
+) -> Result<Option<String>, ReplyError> {
+    let check_window = |window| -> Result<Option<xproto::Window>, ReplyError> {
+        Ok(connection
+            .get_property(
+                false,
+                window,
+                atoms._NET_SUPPORTING_WM_CHECK,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
+            .reply()?
+            .value32()
+            .and_then(|mut value| value.next()))
+    };
+
+    let Some(child) = check_window(root)? else {
+        return Ok(None);
+    };
+
+    // the check window points at itself, unless it's left over from a window manager
+    // that didn't clean up after exiting, then it's gone or something else entirely
+    if !matches!(check_window(child), Ok(Some(window)) if window == child) {
+        return Ok(None);
+    }
+
+    let name = connection
+        .get_property(
+            false,
+            child,
+            atoms._NET_WM_NAME,
+            atoms.UTF8_STRING,
+            0,
+            u32::MAX,
+        )?
+        .reply()?
+        .value;
+
+    Ok(Some(String::from_utf8_lossy(&name).into_owned()))
 }
 
 #[derive(Debug)]
@@ -65,6 +354,7 @@ x11rb::atom_manager! {
         _NET_WM_WINDOW_TYPE_NORMAL,
 
         _NET_CLIENT_LIST,
+        _NET_ACTIVE_WINDOW,
         _NET_DESKTOP_VIEWPORT,
         _NET_DESKTOP_GEOMETRY,
         _NET_NUMBER_OF_DESKTOPS,
@@ -82,6 +372,7 @@ x11rb::atom_manager! {
         _NET_SYSTEM_TRAY_COLORS,
         _NET_SYSTEM_TRAY_ORIENTATION,
         _NET_SYSTEM_TRAY_ORIENTATION_HORZ,
+        _NET_SYSTEM_TRAY_ORIENTATION_VERT,
         _NET_SYSTEM_TRAY_S,
 
         _XEMBED,
@@ -89,9 +380,16 @@ x11rb::atom_manager! {
 
         _NET_WM_NAME,
         WM_NAME,
+        UTF8_STRING,
+
+        _NET_SUPPORTING_WM_CHECK,
+        _NET_SUPPORTED,
 
         WM_PROTOCOLS,
         _NET_WM_PING,
         WM_DELETE_WINDOW,
+
+        _XROOTPMAP_ID,
+        ESETROOT_PMAP_ID,
     }
 }
@@ -0,0 +1,381 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use mdry::color::Color;
+use serde::Deserialize;
+use x11rb::xcb_ffi::XCBConnection;
+
+use crate::widgets::{
+    brightness::Brightness, cpu_usage::CPUUsage, file_watch::FileWatch, pager::Pager,
+    separator::Separator, spacer::Spacer, sys_time::SysTime, sys_tray::SysTray, taskbar::Taskbar,
+    window_title::WindowTitle, Alignment, Widget,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid value for `{key}`: {reason}")]
+    InvalidValue { key: String, reason: String },
+    #[error("Unknown widget type `{0}`")]
+    UnknownWidget(String),
+    #[error("Widget is missing its `type`")]
+    MissingType,
+}
+
+/// The bar's configuration, loaded from `~/.config/shareet/config.toml`
+///
+/// ```toml
+/// [bar]
+/// height = 35
+/// foreground = "#bfbdb6"
+///
+/// [[widget]]
+/// type = "clock"
+/// align = "right"
+/// color = "#bbbbbb"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub bar: BarConfig,
+    /// every widget's table, built in order with a [`Registry`]
+    #[serde(default, rename = "widget")]
+    pub widgets: Vec<toml::Table>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BarConfig {
+    pub height: u16,
+    pub bottom: bool,
+    pub foreground: String,
+    pub background: String,
+    /// space between the screen edges and the outermost widgets
+    pub margin: f32,
+    /// space between neighbouring widgets
+    pub spacing: f32,
+    /// draw the wallpaper under the bar as its background, for setups without a compositor
+    pub pseudo_transparent: bool,
+    /// rounds the corners of the bar, see [`Bar::set_corner_radius`](crate::Bar::set_corner_radius)
+    pub corner_radius: u32,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            height: 35,
+            bottom: false,
+            foreground: String::from("#bfbdb6"),
+            background: String::from("#1a1d24"),
+            margin: 0.,
+            spacing: 0.,
+            pseudo_transparent: false,
+            corner_radius: 0,
+        }
+    }
+}
+
+impl BarConfig {
+    pub fn foreground(&self) -> Result<Color, ConfigError> {
+        parse_color("bar.foreground", &self.foreground)
+    }
+
+    pub fn background(&self) -> Result<Color, ConfigError> {
+        parse_color("bar.background", &self.background)
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/shareet/config.toml`, or `~/.config/shareet/config.toml`
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("shareet").join("config.toml"))
+    }
+
+    /// returns `None` if there's no config file
+    pub fn load() -> Result<Option<Self>, ConfigError> {
+        let Some(path) = Self::path() else {
+            return Ok(None);
+        };
+
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(Self::parse(&content)?))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+/// What widgets get to build themselves from their config table
+pub struct WidgetContext<'a> {
+    pub connection: &'a XCBConnection,
+    pub screen_num: usize,
+    pub bar_width: u32,
+    pub bar_height: u32,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+pub type WidgetConstructor =
+    fn(&toml::Table, &WidgetContext) -> Result<Box<dyn Widget>, crate::Error>;
+
+/// Maps the `type` of a widget table to its constructor
+pub struct Registry {
+    constructors: HashMap<String, WidgetConstructor>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register("pager", |table, context| {
+            Ok(Box::new(Pager::from_config(table, context)?))
+        });
+        registry.register("systray", |table, context| {
+            Ok(Box::new(SysTray::from_config(table, context)?))
+        });
+        registry.register("clock", |table, context| {
+            Ok(Box::new(SysTime::from_config(table, context)?))
+        });
+        registry.register("cpu", |table, context| {
+            Ok(Box::new(CPUUsage::from_config(table, context)?))
+        });
+        registry.register("brightness", |table, context| {
+            Ok(Box::new(Brightness::from_config(table, context)?))
+        });
+        registry.register("separator", |table, context| {
+            Ok(Box::new(Separator::from_config(table, context)?))
+        });
+        registry.register("spacer", |table, context| {
+            Ok(Box::new(Spacer::from_config(table, context)?))
+        });
+        registry.register("taskbar", |table, context| {
+            Ok(Box::new(Taskbar::from_config(table, context)?))
+        });
+        registry.register("title", |table, context| {
+            Ok(Box::new(WindowTitle::from_config(table, context)?))
+        });
+        registry.register("file", |table, context| {
+            Ok(Box::new(FileWatch::from_config(table, context)?))
+        });
+
+        registry
+    }
+}
+
+impl Registry {
+    /// a registry without the built-in widgets
+    pub fn empty() -> Self {
+        Self {
+            constructors: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, constructor: WidgetConstructor) {
+        self.constructors.insert(name.to_string(), constructor);
+    }
+
+    pub fn build(
+        &self,
+        table: &toml::Table,
+        context: &WidgetContext,
+    ) -> Result<Box<dyn Widget>, crate::Error> {
+        let constructor = self.constructor(table)?;
+
+        let mut widget = constructor(table, context)?;
+        // every widget has its own default, which is only replaced when `align` is set
+        if let Some(alignment) = get_alignment(table)? {
+            widget.set_alignment(alignment);
+        }
+
+        Ok(widget)
+    }
+
+    /// the constructor for the `type` of `table`
+    fn constructor(&self, table: &toml::Table) -> Result<WidgetConstructor, ConfigError> {
+        let name = table
+            .get("type")
+            .and_then(toml::Value::as_str)
+            .ok_or(ConfigError::MissingType)?;
+
+        self.constructors
+            .get(name)
+            .copied()
+            .ok_or_else(|| ConfigError::UnknownWidget(name.to_string()))
+    }
+
+    pub fn build_all(
+        &self,
+        tables: &[toml::Table],
+        context: &WidgetContext,
+    ) -> Result<Vec<Box<dyn Widget>>, crate::Error> {
+        tables
+            .iter()
+            .map(|table| self.build(table, context))
+            .collect()
+    }
+}
+
+pub(crate) fn invalid(key: &str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::InvalidValue {
+        key: key.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn parse_color(key: &str, value: &str) -> Result<Color, ConfigError> {
+    Color::hex(value).ok_or_else(|| {
+        invalid(
+            key,
+            format!("`{value}` is not a #rrggbb or #rrggbbaa color"),
+        )
+    })
+}
+
+pub fn get_color(table: &toml::Table, key: &str, default: Color) -> Result<Color, ConfigError> {
+    match table.get(key) {
+        Some(toml::Value::String(value)) => parse_color(key, value),
+        Some(_) => Err(invalid(key, "expected a string")),
+        None => Ok(default),
+    }
+}
+
+/// accepts both integers and floats
+pub fn get_f32(table: &toml::Table, key: &str, default: f32) -> Result<f32, ConfigError> {
+    match table.get(key) {
+        Some(toml::Value::Float(value)) => Ok(*value as f32),
+        Some(toml::Value::Integer(value)) => Ok(*value as f32),
+        Some(_) => Err(invalid(key, "expected a number")),
+        None => Ok(default),
+    }
+}
+
+pub fn get_u32(table: &toml::Table, key: &str, default: u32) -> Result<u32, ConfigError> {
+    match table.get(key) {
+        Some(toml::Value::Integer(value)) => {
+            u32::try_from(*value).map_err(|_| invalid(key, "expected a positive integer"))
+        }
+        Some(_) => Err(invalid(key, "expected an integer")),
+        None => Ok(default),
+    }
+}
+
+pub fn get_bool(table: &toml::Table, key: &str, default: bool) -> Result<bool, ConfigError> {
+    match table.get(key) {
+        Some(toml::Value::Boolean(value)) => Ok(*value),
+        Some(_) => Err(invalid(key, "expected a boolean")),
+        None => Ok(default),
+    }
+}
+
+pub fn get_str<'a>(
+    table: &'a toml::Table,
+    key: &str,
+    default: &'a str,
+) -> Result<&'a str, ConfigError> {
+    match table.get(key) {
+        Some(toml::Value::String(value)) => Ok(value),
+        Some(_) => Err(invalid(key, "expected a string")),
+        None => Ok(default),
+    }
+}
+
+/// the `align` key of any widget, either "left", "center" or "right"
+fn get_alignment(table: &toml::Table) -> Result<Option<Alignment>, ConfigError> {
+    match get_str(table, "align", "")? {
+        "" => Ok(None),
+        "left" => Ok(Some(Alignment::Left)),
+        "center" => Ok(Some(Alignment::Center)),
+        "right" => Ok(Some(Alignment::Right)),
+        other => Err(invalid(
+            "align",
+            format!("`{other}` is not one of left, center or right"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> toml::Table {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn parse_without_bar_uses_the_defaults() {
+        let config = Config::parse("[[widget]]\ntype = \"clock\"\n").unwrap();
+
+        assert_eq!(config.bar.height, 35);
+        assert!(!config.bar.bottom);
+        assert_eq!(config.widgets.len(), 1);
+        assert_eq!(config.widgets[0]["type"].as_str(), Some("clock"));
+    }
+
+    #[test]
+    fn parse_with_bar_keeps_the_defaults_it_doesnt_set() {
+        let config = Config::parse("[bar]\nheight = 24\nbottom = true\n").unwrap();
+
+        assert_eq!(config.bar.height, 24);
+        assert!(config.bar.bottom);
+        assert_eq!(config.bar.foreground, "#bfbdb6");
+        assert!(config.widgets.is_empty());
+    }
+
+    #[test]
+    fn build_needs_a_known_type() {
+        let registry = Registry::default();
+
+        let missing = registry.constructor(&table("align = \"left\""));
+        assert!(matches!(missing, Err(ConfigError::MissingType)));
+
+        let unknown = registry.constructor(&table("type = \"weather\""));
+        assert!(matches!(unknown, Err(ConfigError::UnknownWidget(name)) if name == "weather"));
+
+        assert!(registry.constructor(&table("type = \"clock\"")).is_ok());
+    }
+
+    #[test]
+    fn alignments() {
+        let align = |value: &str| get_alignment(&table(&format!("align = \"{value}\"")));
+
+        assert_eq!(align("left").unwrap(), Some(Alignment::Left));
+        assert_eq!(align("center").unwrap(), Some(Alignment::Center));
+        assert_eq!(align("right").unwrap(), Some(Alignment::Right));
+        assert!(matches!(
+            align("middle"),
+            Err(ConfigError::InvalidValue { key, .. }) if key == "align"
+        ));
+        assert_eq!(get_alignment(&table("")).unwrap(), None);
+    }
+
+    #[test]
+    fn getters_reject_the_wrong_type() {
+        let table = table("string = \"5\"\nnumber = 5\nnegative = -1\n");
+
+        assert!(get_color(&table, "number", Color::rgb(0, 0, 0)).is_err());
+        assert!(get_f32(&table, "string", 0.).is_err());
+        assert!(get_u32(&table, "string", 0).is_err());
+        assert!(get_bool(&table, "number", false).is_err());
+        assert!(get_str(&table, "number", "").is_err());
+
+        assert!(matches!(
+            get_u32(&table, "negative", 0),
+            Err(ConfigError::InvalidValue { key, .. }) if key == "negative"
+        ));
+        assert_eq!(get_u32(&table, "number", 0).unwrap(), 5);
+        assert_eq!(get_f32(&table, "number", 0.).unwrap(), 5.);
+        assert_eq!(get_u32(&table, "missing", 7).unwrap(), 7);
+    }
+}
@@ -0,0 +1,127 @@
+use mdry::shapes::ClipRect;
+
+/// Where the widgets of a bar go, see [`Bar::layout`](crate::Bar::layout)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    /// where each widget starts, in the order of the widgets
+    pub offsets: Vec<f32>,
+    /// the part of the bar each widget can draw in, empty for center widgets
+    /// that were pushed out by the left and right groups
+    pub spans: Vec<ClipRect>,
+    /// the left widgets and the spacing between them, without the margin
+    pub left_width: f32,
+    /// the right widgets and the spacing between them, without the margin
+    pub right_width: f32,
+    /// whether the widgets don't fit in the bar even at their minimum sizes,
+    /// the groups overlap when this is set
+    pub overflow: bool,
+}
+
+/// How much space a widget wants, see [`Widget::size_hint`](crate::widgets::Widget::size_hint)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeHint {
+    pub preferred: f32,
+    pub min: f32,
+    /// `None` if the widget can grow as much as it's given
+    pub max: Option<f32>,
+}
+
+impl SizeHint {
+    fn clamped_preferred(&self) -> f32 {
+        let preferred = self.preferred.max(self.min);
+        match self.max {
+            Some(max) => preferred.min(max.max(self.min)),
+            None => preferred,
+        }
+    }
+}
+
+/// Gives every widget a size that fits in `available` if possible
+///
+/// - if the preferred sizes fit, the leftover space is split between
+///   the widgets that can grow, up to their max
+/// - if only the minimum sizes fit, the widgets are compressed
+///   in proportion to how much they can shrink
+/// - otherwise every widget gets its minimum and the bar overflows
+pub fn distribute(hints: &[SizeHint], available: f32) -> Vec<f32> {
+    let mut sizes: Vec<f32> = hints.iter().map(SizeHint::clamped_preferred).collect();
+    let total: f32 = sizes.iter().sum();
+
+    if total <= available {
+        grow(hints, &mut sizes, available - total);
+        return sizes;
+    }
+
+    let min_total: f32 = hints.iter().map(|hint| hint.min).sum();
+    if min_total >= available {
+        return hints.iter().map(|hint| hint.min).collect();
+    }
+
+    // shrink every widget by the same fraction of its shrinkable space
+    let shrinkable = total - min_total;
+    let fraction = (total - available) / shrinkable;
+    for (size, hint) in sizes.iter_mut().zip(hints) {
+        *size -= (*size - hint.min) * fraction;
+    }
+
+    sizes
+}
+
+fn grow(hints: &[SizeHint], sizes: &mut [f32], mut leftover: f32) {
+    loop {
+        let growable: Vec<usize> = (0..sizes.len())
+            .filter(|&i| hints[i].max.map_or(true, |max| sizes[i] < max))
+            .collect();
+
+        if growable.is_empty() || leftover <= f32::EPSILON {
+            return;
+        }
+
+        let share = leftover / growable.len() as f32;
+        let mut changed = false;
+        for i in growable {
+            let grown = match hints[i].max {
+                Some(max) => (sizes[i] + share).min(max),
+                None => sizes[i] + share,
+            };
+            changed |= grown != sizes[i];
+            leftover -= grown - sizes[i];
+            sizes[i] = grown;
+        }
+
+        // a share too small to change any size would never use up the rest
+        if !changed {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(size: f32) -> SizeHint {
+        SizeHint {
+            preferred: size,
+            min: size,
+            max: Some(size),
+        }
+    }
+
+    const FLEX: SizeHint = SizeHint {
+        preferred: 0.,
+        min: 0.,
+        max: None,
+    };
+
+    #[test]
+    fn grow_stops_when_the_rest_is_too_small_to_share() {
+        let hints = [FLEX, fixed(60.5), FLEX, fixed(40.24), FLEX, FLEX];
+
+        let sizes = distribute(&hints, 1920.);
+
+        assert_eq!(sizes[1], 60.5);
+        assert_eq!(sizes[3], 40.24);
+        assert!((sizes.iter().sum::<f32>() - 1920.).abs() < 0.01);
+    }
+}
@@ -1,45 +1,925 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam::channel::{Receiver, Sender};
+use mdry::color::Color;
+use mdry::keyboard::Keymap;
+use mdry::shapes::ClipRect;
 use mdry::window::{Window, WindowType};
 use mdry::State;
 
-use widgets::Widget;
-use x11rb::connection::Connection;
+use layout::{Layout, SizeHint};
+use log::{error, warn};
+use scheduler::Scheduler;
+use widgets::{Alignment, EventInterest, Widget};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::errors::ConnectionError;
+use x11rb::protocol::shape::{self, ConnectionExt as _, SK, SO};
 use x11rb::protocol::xproto::{
-    AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, WindowClass,
+    AtomEnum, ChangeWindowAttributesAux, ColormapAlloc, ConfigureWindowAux, ConnectionExt as _,
+    CreateWindowAux, EventMask, GrabMode, KeyPressEvent, Keycode, Keysym, ModMask, NotifyDetail,
+    PropMode, Screen, VisualClass, Visualid, Window as Xwindow, WindowClass,
 };
+use x11rb::protocol::xproto::{ClipOrdering, Rectangle};
+use x11rb::protocol::Event;
 use x11rb::wrapper::ConnectionExt as _;
 use x11rb::xcb_ffi::XCBConnection;
 use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT};
 
+pub mod config;
+pub mod layout;
+pub mod scheduler;
+mod trace;
+mod wallpaper;
 pub mod widgets;
 
 pub type Error = Box<dyn std::error::Error>;
 
+/// the title and `WM_CLASS` of the bar window, to match it in window manager rules
+pub const WINDOW_NAME: &str = "shareet";
+
+/// how often [`Widget::tick`] is called while a widget animates, ~30 times a second
+const ANIMATION_INTERVAL: Duration = Duration::from_millis(33);
+
 pub trait Vertex: bytemuck::Pod + bytemuck::Zeroable {
     fn desc() -> wgpu::VertexBufferLayout<'static>;
 }
 
+/// why [`Bar::run`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// the window was closed or [`Bar::shutdown_sender`] was used
+    Shutdown,
+    /// the connection to the X server failed, e.g. because it was restarted,
+    /// the bar can't be used anymore but a new one can be made after reconnecting
+    ConnectionLost,
+}
+
+/// what [`Bar::grab_key`] calls, returns whether the bar should be redrawn
+pub type KeyCallback<'a> = Box<dyn FnMut(&mut [Box<dyn Widget>], &mut State) -> bool + 'a>;
+
+/// a key grabbed on the root window with [`Bar::grab_key`]
+struct KeyGrab<'a> {
+    keycodes: Vec<Keycode>,
+    modifiers: u16,
+    /// the lock modifiers the key is also grabbed with, ignored when matching presses
+    locks: u16,
+    callback: KeyCallback<'a>,
+}
+
 pub struct Bar<'a> {
     pub state: State<'a>,
     pub widgets: Vec<Box<dyn Widget>>,
+    pub background: Color,
+    /// space between the screen edges and the left and right widgets
+    margin: f32,
+    /// space between neighbouring widgets of the same alignment
+    spacing: f32,
+    /// where each widget was drawn last frame
+    spans: Vec<ClipRect>,
+    /// the widget under the pointer
+    hovered: Option<usize>,
+    /// the width the bar was created with, a dock is only narrowed to fit a smaller screen
+    width: u32,
+    /// the window manager's frame around the bar, when it reparented it
+    frame: Option<Xwindow>,
+    /// whether the wallpaper under the bar is drawn as its background
+    pseudo_transparent: bool,
+    /// the wallpaper or the bar's position changed since it was last read
+    wallpaper_dirty: bool,
+    /// the radius the window's corners are cut to, see [`Bar::set_corner_radius`]
+    corner_radius: u32,
+    /// global shortcuts, see [`Bar::grab_key`]
+    key_grabs: Vec<KeyGrab<'a>>,
+    /// logs every X event before it's handled, see [`Bar::set_trace_events`]
+    trace_events: bool,
+    shutdown_sender: Sender<()>,
+    shutdown_receiver: Receiver<()>,
 }
 
 impl<'a> Bar<'a> {
-    pub async fn new(window: mdry::window::Window<'a>) -> Bar<'a> {
-        let state = State::new(window).await;
+    pub async fn new(
+        window: mdry::window::Window<'a>,
+        sample_count: u32,
+        present_mode: wgpu::PresentMode,
+    ) -> Bar<'a> {
+        let width = window.width;
+        let state = State::new(window, sample_count, present_mode).await;
+        let (shutdown_sender, shutdown_receiver) = crossbeam::channel::bounded(1);
         Self {
             state,
             widgets: vec![],
+            background: Color::rgb(26, 29, 36),
+            margin: 0.,
+            spacing: 0.,
+            spans: Vec::new(),
+            hovered: None,
+            width,
+            frame: None,
+            pseudo_transparent: false,
+            wallpaper_dirty: false,
+            corner_radius: 0,
+            key_grabs: Vec::new(),
+            trace_events: std::env::var_os("SHAREET_TRACE").is_some(),
+            shutdown_sender,
+            shutdown_receiver,
+        }
+    }
+
+    pub fn set_margin(&mut self, margin: f32) {
+        self.margin = margin;
+    }
+
+    pub fn set_spacing(&mut self, spacing: f32) {
+        self.spacing = spacing;
+    }
+
+    /// draws the part of the wallpaper under the bar as its background, the fake transparency
+    /// for setups without a compositor, the background color is used when there's no wallpaper
+    pub fn set_pseudo_transparent(&mut self, pseudo_transparent: bool) {
+        self.pseudo_transparent = pseudo_transparent;
+        self.wallpaper_dirty = pseudo_transparent;
+        if !pseudo_transparent {
+            self.state.set_background_texture(None);
+        }
+    }
+
+    /// rounds the corners of the bar by cutting them out of the window with the X Shape
+    /// extension, so it works without a compositor, 0 makes the window rectangular again
+    ///
+    /// the cut edges aren't anti-aliased, and a compositor's shadows follow the new shape
+    pub fn set_corner_radius(&mut self, radius: u32) -> Result<(), Error> {
+        self.corner_radius = radius;
+        self.apply_corner_radius()
+    }
+
+    fn apply_corner_radius(&self) -> Result<(), Error> {
+        let window = self.state.window();
+        let connection = window.connection;
+        if connection
+            .extension_information(shape::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            warn!("the X server doesn't support the Shape extension, the corners stay square");
+            return Ok(());
+        }
+
+        if self.corner_radius == 0 {
+            connection.shape_mask(SO::SET, SK::BOUNDING, window.xid, 0, 0, x11rb::NONE)?;
+            return Ok(());
+        }
+
+        let rects = rounded_rect_region(self.state.width, self.state.height, self.corner_radius);
+        connection.shape_rectangles(
+            SO::SET,
+            SK::BOUNDING,
+            ClipOrdering::Y_SORTED,
+            window.xid,
+            0,
+            0,
+            &rects,
+        )?;
+
+        Ok(())
+    }
+
+    /// calls `callback` whenever `keysym` is pressed with exactly `modifiers`, anywhere on the
+    /// screen, whatever the state of Caps Lock and Num Lock, until the bar exits
+    ///
+    /// fails if another client already grabbed the same key
+    pub fn grab_key(
+        &mut self,
+        keysym: Keysym,
+        modifiers: ModMask,
+        callback: impl FnMut(&mut [Box<dyn Widget>], &mut State) -> bool + 'a,
+    ) -> Result<(), Error> {
+        let connection = self.state.window().connection;
+        let root = connection.setup().roots[self.state.window().screen_num].root;
+
+        let keymap = Keymap::new(connection)?;
+        let keycodes = keymap.keycodes(keysym);
+        if keycodes.is_empty() {
+            return Err(format!("no key produces the keysym {keysym:#x}").into());
+        }
+
+        let locks = u16::from(ModMask::LOCK) | u16::from(keymap.num_lock_mask(connection)?);
+        let grab = KeyGrab {
+            keycodes,
+            modifiers: u16::from(modifiers),
+            locks,
+            callback: Box::new(callback),
+        };
+
+        for (keycode, modifiers) in grab.combinations() {
+            connection
+                .grab_key(
+                    false,
+                    root,
+                    ModMask::from(modifiers),
+                    keycode,
+                    GrabMode::ASYNC,
+                    GrabMode::ASYNC,
+                )?
+                .check()?;
+        }
+
+        self.key_grabs.push(grab);
+
+        Ok(())
+    }
+
+    /// logs every X event with its window ids and atom names, for debugging the tray and pager,
+    /// also turned on by setting `SHAREET_TRACE`
+    pub fn set_trace_events(&mut self, trace_events: bool) {
+        self.trace_events = trace_events;
+    }
+
+    /// makes [`Bar::run`] return after cleaning up the widgets, safe to use from a signal handler
+    pub fn shutdown_sender(&self) -> Sender<()> {
+        self.shutdown_sender.clone()
+    }
+
+    /// sets up the widgets, then handles X events, timers and redraws until the window
+    /// is closed, [`Bar::shutdown_sender`] is used, or the connection fails
+    pub fn run(&mut self, connection: Arc<XCBConnection>) -> Result<Exit, Error> {
+        let screen_num = self.state.window().screen_num;
+        let root = connection.setup().roots[screen_num].root;
+
+        // widgets like the pager follow properties on the root window,
+        // and the root is resized when the screen resolution changes
+        let change = ChangeWindowAttributesAux::new()
+            .event_mask(EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY);
+
+        connection
+            .change_window_attributes(root, &change)?
+            .check()?;
+
+        let mut keymap = Keymap::new(&*connection)?;
+
+        let (event_sender, event_receiver) = crossbeam::channel::unbounded::<Event>();
+        let (redraw_sender, redraw_receiver) = crossbeam::channel::unbounded::<()>();
+        let (lost_sender, lost_receiver) = crossbeam::channel::bounded::<()>(1);
+        let (mut scheduler, timer_receiver) = Scheduler::new();
+
+        for widget in self.widgets.iter_mut() {
+            widget
+                .setup(
+                    &mut self.state,
+                    &connection,
+                    screen_num,
+                    redraw_sender.clone(),
+                    &scheduler,
+                )
+                .map_err(|e| format!("{}: {e}", widget.name()))?;
+        }
+
+        {
+            let connection = connection.clone();
+            let atom_cache = self
+                .trace_events
+                .then(|| self.state.window().atom_cache.clone());
+            std::thread::spawn(move || {
+                // the X server went away, e.g. on logout, nothing more will come from it
+                if let Err(e) = read_events(&connection, &event_sender, atom_cache.as_deref()) {
+                    error!("lost the connection to the X server: {e}");
+                    let _ = lost_sender.try_send(());
+                }
+            });
+        }
+        // only ticks while a widget animates, so an idle bar doesn't wake up 30 times a second
+        let mut animation_ticker = crossbeam::channel::tick(ANIMATION_INTERVAL);
+        let mut ticking = true;
+
+        // set when what's on screen can't be kept, like after an Expose,
+        // so the next redraw from the channel draws every widget
+        let mut full_redraw = true;
+
+        let mut exit = Exit::Shutdown;
+
+        'event_loop: loop {
+            crossbeam::select! {
+                recv(self.shutdown_receiver) -> _ => break 'event_loop,
+                recv(lost_receiver) -> _ => {
+                    exit = Exit::ConnectionLost;
+                    break 'event_loop;
+                },
+                recv(animation_ticker) -> now => {
+                    let Ok(now) = now else {
+                        continue;
+                    };
+
+                    let changed: Vec<usize> = self
+                        .widgets
+                        .iter_mut()
+                        .enumerate()
+                        .filter_map(|(i, widget)| widget.tick(now, &mut self.state).then_some(i))
+                        .collect();
+
+                    if changed.is_empty() {
+                        animation_ticker = crossbeam::channel::never();
+                        ticking = false;
+                        continue 'event_loop;
+                    }
+
+                    if !self.redraw(&connection, screen_num, Damage::Widgets(changed))? {
+                        break 'event_loop;
+                    }
+                },
+                recv(timer_receiver) -> token => {
+                    let Ok(token) = token else {
+                        continue;
+                    };
+
+                    let changed: Vec<usize> = self
+                        .widgets
+                        .iter_mut()
+                        .enumerate()
+                        .filter_map(|(i, widget)| widget.on_timer(token, &mut self.state).then_some(i))
+                        .collect();
+
+                    if !changed.is_empty() && !self.redraw(&connection, screen_num, Damage::Widgets(changed))? {
+                        break 'event_loop;
+                    }
+                },
+                recv(event_receiver) -> event => {
+                    if let Ok(event) = event {
+
+                    match event {
+                        Event::ClientMessage(event) => {
+                            if event.data.as_data32()[0] == self.state.window().atoms.WM_DELETE_WINDOW {
+                                break 'event_loop;
+                            }
+                        }
+                        Event::PropertyNotify(event) if event.window == root => {
+                            let atoms = self.state.window().atoms;
+                            if event.atom == atoms._XROOTPMAP_ID || event.atom == atoms.ESETROOT_PMAP_ID {
+                                self.wallpaper_dirty = self.pseudo_transparent;
+                                full_redraw = true;
+                            }
+                            redraw_sender.send(()).unwrap();
+                        }
+                        Event::ConfigureNotify(event) if event.window == root => {
+                            // our own ConfigureNotify resizes the state and redraws
+                            self.fit_to_screen(&connection, event.width, event.height)?;
+                        }
+                        Event::ReparentNotify(event) if event.window == self.state.window().xid => {
+                            self.frame = (event.parent != root).then_some(event.parent);
+                            self.sync_position(&connection, root)?;
+                        }
+                        Event::ConfigureNotify(configure)
+                            if self.frame.is_some() && configure.window == self.state.window().xid =>
+                        {
+                            if self.input(&connection, &event) {
+                                full_redraw = true;
+                                redraw_sender.send(())?;
+                            }
+                            // the x and y in the event are relative to the frame
+                            self.sync_position(&connection, root)?;
+                        }
+                        Event::Expose(_) | Event::ConfigureNotify(_) => {
+                            if self.input(&connection, &event) {
+                                full_redraw = true;
+                                redraw_sender.send(())?;
+                            }
+                        }
+                        Event::MotionNotify(event) if event.event == self.state.window().xid => {
+                            self.set_hovered(&connection, Some((event.event_x as f32, event.event_y as f32)));
+                        }
+                        Event::LeaveNotify(event) => {
+                            // moving into a child, like a tray icon, keeps the pointer on the bar
+                            if event.event == self.state.window().xid && event.detail != NotifyDetail::INFERIOR {
+                                self.set_hovered(&connection, None);
+                            }
+                            redraw_sender.send(())?;
+                        }
+                        Event::EnterNotify(event) => {
+                            if event.event == self.state.window().xid {
+                                self.set_hovered(&connection, Some((event.event_x as f32, event.event_y as f32)));
+                            }
+                            redraw_sender.send(())?;
+                        }
+                        Event::MappingNotify(_) => keymap = Keymap::new(&*connection)?,
+                        Event::KeyPress(event) if event.event == root => {
+                            let grab = self.key_grabs.iter_mut().find(|grab| grab.matches(&event));
+                            if let Some(grab) = grab {
+                                if (grab.callback)(&mut self.widgets, &mut self.state) {
+                                    redraw_sender.send(())?;
+                                }
+                            }
+                        }
+                        Event::KeyPress(event) => {
+                            if let Some(keysym) = keymap.keysym(event.detail, event.state) {
+                                for widget in self.widgets.iter_mut() {
+                                    if let Err(e) = widget.on_key(keysym, event.state, &mut self.state) {
+                                        error!("{}: {e}", widget.name());
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    let interest = EventInterest::of(&event);
+                    for widget in self.widgets.iter_mut() {
+                        if !widget.event_mask().intersects(interest) {
+                            continue;
+                        }
+                        if let Err(e) =
+                            widget.on_event(&connection, screen_num, &mut self.state, event.clone(), redraw_sender.clone())
+                        {
+                            error!("{}: {e}", widget.name());
+                        }
+                    }
+                    }
+                },
+                recv(redraw_receiver) -> _ => {
+                    let damage = if std::mem::take(&mut full_redraw) {
+                        Damage::Full
+                    } else {
+                        // widgets that don't track their changes always count as changed
+                        Damage::Widgets(
+                            self.widgets
+                                .iter()
+                                .enumerate()
+                                .filter_map(|(i, widget)| widget.requires_redraw().then_some(i))
+                                .collect(),
+                        )
+                    };
+
+                    if !self.redraw(&connection, screen_num, damage)? {
+                        break 'event_loop;
+                    }
+                }
+            }
+
+            // anything but a tick may have started an animation, tick again to find out
+            if !ticking {
+                animation_ticker = crossbeam::channel::tick(ANIMATION_INTERVAL);
+                ticking = true;
+            }
+        }
+
+        scheduler.shutdown();
+
+        // there's nothing left to clean up on a server that's gone
+        if exit == Exit::ConnectionLost {
+            return Ok(exit);
+        }
+
+        for grab in self.key_grabs.iter() {
+            for (keycode, modifiers) in grab.combinations() {
+                connection.ungrab_key(keycode, root, ModMask::from(modifiers))?;
+            }
+        }
+
+        for widget in self.widgets.iter_mut() {
+            if let Err(e) = widget.destroy(&connection, screen_num, &mut self.state) {
+                error!("{}: {e}", widget.name());
+            }
+        }
+
+        connection.flush()?;
+
+        Ok(exit)
+    }
+
+    /// passes the window geometry events to the state, and lets the widgets know
+    /// when the bar changed size, returns whether the bar should be redrawn
+    fn input(&mut self, connection: &XCBConnection, event: &Event) -> bool {
+        let size = (self.state.width, self.state.height);
+        if !self.state.input(event) {
+            return false;
+        }
+
+        // the bar may have moved over another part of the wallpaper
+        if matches!(event, Event::ConfigureNotify(_)) {
+            self.wallpaper_dirty = self.pseudo_transparent;
+        }
+
+        let (width, height) = (self.state.width, self.state.height);
+        if (width, height) != size {
+            if self.corner_radius > 0 {
+                if let Err(e) = self.apply_corner_radius() {
+                    error!("could not round the corners: {e}");
+                }
+            }
+            for widget in self.widgets.iter_mut() {
+                if let Err(e) = widget.on_resize(connection, width, height, &mut self.state) {
+                    error!("{}: {e}", widget.name());
+                }
+            }
+        }
+
+        true
+    }
+
+    /// fires the enter and leave callbacks when the pointer moved to another widget,
+    /// `pointer` is `None` when the pointer left the bar
+    fn set_hovered(&mut self, connection: &XCBConnection, pointer: Option<(f32, f32)>) {
+        let hovered =
+            pointer.and_then(|(x, y)| self.spans.iter().position(|span| span.contains(x, y)));
+
+        if hovered == self.hovered {
+            return;
+        }
+
+        if let Some(widget) = self.hovered.and_then(|i| self.widgets.get_mut(i)) {
+            if let Err(e) = widget.on_pointer_leave(connection, &mut self.state) {
+                error!("{}: {e}", widget.name());
+            }
+        }
+
+        if let Some(widget) = hovered.and_then(|i| self.widgets.get_mut(i)) {
+            if let Err(e) = widget.on_pointer_enter(connection, &mut self.state) {
+                error!("{}: {e}", widget.name());
+            }
         }
+
+        self.hovered = hovered;
+    }
+
+    /// keeps a dock within the new screen width, moves it back to its edge,
+    /// and reserves the space under it again
+    fn fit_to_screen(
+        &mut self,
+        connection: &XCBConnection,
+        screen_width: u16,
+        screen_height: u16,
+    ) -> Result<(), Error> {
+        let window = self.state.window_mut();
+        let WindowType::Dock { bottom, .. } = window.window_type else {
+            return Ok(());
+        };
+
+        let x = window.x as i16;
+        let width = (self.width as u16).min(screen_width.saturating_sub(x.max(0) as u16));
+        let (y, struts) = dock_struts(screen_height, x, width, window.height as u16, bottom);
+
+        connection.configure_window(
+            window.xid,
+            &ConfigureWindowAux::new().y(y as i32).width(width as u32),
+        )?;
+        set_struts(connection, window.xid, &window.atoms, &struts)?;
+        connection.flush()?;
+
+        window.window_type = WindowType::Dock { bottom, struts };
+
+        Ok(())
+    }
+
+    /// reads where the window is relative to the root, since its x and y are relative to
+    /// the frame once the window manager reparented it, and reserves the space under it again
+    fn sync_position(&mut self, connection: &XCBConnection, root: Xwindow) -> Result<(), Error> {
+        let window = self.state.window_mut();
+
+        let position = connection
+            .translate_coordinates(window.xid, root, 0, 0)?
+            .reply()?;
+        window.x = position.dst_x.into();
+        window.y = position.dst_y.into();
+
+        let WindowType::Dock { bottom, .. } = window.window_type else {
+            return Ok(());
+        };
+
+        let screen_height = connection.setup().roots[window.screen_num].height_in_pixels;
+        let (_, struts) = dock_struts(
+            screen_height,
+            position.dst_x,
+            window.width as u16,
+            window.height as u16,
+            bottom,
+        );
+        set_struts(connection, window.xid, &window.atoms, &struts)?;
+        connection.flush()?;
+
+        window.window_type = WindowType::Dock { bottom, struts };
+        self.wallpaper_dirty = self.pseudo_transparent;
+
+        Ok(())
+    }
+
+    /// shows the part of the wallpaper under the bar as its background,
+    /// or the background color when there's no wallpaper to read
+    fn load_wallpaper(&mut self, connection: &XCBConnection, screen_num: usize) {
+        self.wallpaper_dirty = false;
+
+        let window = self.state.window();
+        let image = wallpaper::read_wallpaper(
+            connection,
+            screen_num,
+            &window.atoms,
+            (window.x as i16, window.y as i16),
+            (window.width as u16, window.height as u16),
+        );
+
+        let texture = match image {
+            Ok(Some(image)) => self.state.create_texture(&image),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("could not read the wallpaper: {e}");
+                None
+            }
+        };
+        self.state.set_background_texture(texture);
+    }
+
+    /// the sizes and positions the widgets get on the next redraw, without drawing them
+    pub fn layout(&mut self) -> Layout {
+        let width = self.state.width as f32;
+        let height = self.state.height as f32;
+        let mut roffset = self.margin;
+        let mut loffset = self.margin;
+        let hints: Vec<SizeHint> = self
+            .widgets
+            .iter_mut()
+            .map(|widget| {
+                let preferred = widget.size(&mut self.state);
+                let (min, max) = widget.size_hint(&mut self.state, preferred);
+                SizeHint {
+                    preferred,
+                    min,
+                    max,
+                }
+            })
+            .collect();
+        let alignments: Vec<Alignment> = self
+            .widgets
+            .iter()
+            .map(|widget| widget.alignment())
+            .collect();
+        // the spacing between the widgets of each group
+        let gaps = |group: Alignment| {
+            let count = alignments.iter().filter(|&&a| a == group).count();
+            count.saturating_sub(1) as f32 * self.spacing
+        };
+        let center_gaps = gaps(Alignment::Center);
+        let available =
+            width - 2. * self.margin - gaps(Alignment::Left) - gaps(Alignment::Right) - center_gaps;
+        let sizes = layout::distribute(&hints, available.max(0.));
+        let overflow = sizes.iter().sum::<f32>() > available;
+        // the widgets of a group and the spacing between them
+        let group_width = |group: Alignment| {
+            sizes
+                .iter()
+                .zip(&alignments)
+                .filter(|(_, &alignment)| alignment == group)
+                .map(|(size, _)| size)
+                .sum::<f32>()
+                + gaps(group)
+        };
+        let left_width = group_width(Alignment::Left);
+        let right_width = group_width(Alignment::Right);
+        let center_total: f32 = sizes
+            .iter()
+            .zip(&alignments)
+            .filter(|(_, alignment)| matches!(alignment, Alignment::Center))
+            .map(|(size, _)| size)
+            .sum::<f32>()
+            + center_gaps;
+        let mut coffset = (width - center_total) / 2.;
+        let offsets: Vec<f32> = sizes
+            .iter()
+            .zip(&alignments)
+            .map(|(&size, alignment)| match alignment {
+                Alignment::Left => {
+                    let offset = loffset;
+                    loffset += size + self.spacing;
+                    offset
+                }
+                Alignment::Right => {
+                    roffset += size;
+                    let offset = width - roffset;
+                    roffset += self.spacing;
+                    offset
+                }
+                Alignment::Center => {
+                    let offset = coffset;
+                    coffset += size + self.spacing;
+                    offset
+                }
+            })
+            .collect();
+        let spans: Vec<ClipRect> = sizes
+            .iter()
+            .zip(&offsets)
+            .zip(&alignments)
+            .map(|((&size, &offset), alignment)| match alignment {
+                // the center group has the lowest priority,
+                // so it only gets what's left between the left and right groups
+                Alignment::Center => {
+                    let start = offset.max(loffset);
+                    let end = (offset + size).min(width - roffset);
+                    ClipRect {
+                        x: start,
+                        y: 0.,
+                        width: end - start,
+                        height,
+                    }
+                }
+                Alignment::Left | Alignment::Right => ClipRect {
+                    x: offset,
+                    y: 0.,
+                    width: size,
+                    height,
+                },
+            })
+            .collect();
+
+        Layout {
+            offsets,
+            spans,
+            left_width,
+            right_width,
+            overflow,
+        }
+    }
+
+    /// lays out and draws the widgets, then renders the frame,
+    /// returns `false` when the bar can't keep rendering
+    fn redraw(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        damage: Damage,
+    ) -> Result<bool, Error> {
+        if self.wallpaper_dirty {
+            self.load_wallpaper(connection, screen_num);
+        }
+
+        let Layout { offsets, spans, .. } = self.layout();
+
+        // only the changed widgets can be redrawn if nothing moved, the last frame
+        // is still there to draw over, and the background color covers what they drew before
+        let partial = spans == self.spans
+            && self.state.retains_frame()
+            && self.background.rgba_f32()[3] >= 1.
+            && self.state.background_texture().is_none();
+        let region = match damage {
+            Damage::Widgets(changed) if partial => {
+                let Some(region) = changed
+                    .iter()
+                    .map(|&i| spans[i])
+                    .filter(|span| !span.is_empty())
+                    .reduce(|a, b| a.union(&b))
+                else {
+                    // nothing visible changed
+                    return Ok(true);
+                };
+                Some(region)
+            }
+            _ => None,
+        };
+
+        match region {
+            Some(region) => {
+                self.state.clear_region(region, self.background);
+                self.state.push_clip(region);
+            }
+            None => self.state.clear_background(self.background),
+        }
+
+        for ((widget, span), offset) in self.widgets.iter_mut().zip(&spans).zip(offsets) {
+            if span.is_empty() || region.is_some_and(|region| span.intersect(&region).is_empty()) {
+                continue;
+            }
+            // keep widgets from drawing over their neighbors,
+            // and let them draw from their own left edge
+            self.state.push_clip(*span);
+            self.state.push_translation(offset, 0.);
+            let result = widget.draw(connection, screen_num, &mut self.state, offset);
+            self.state.pop_translation();
+            self.state.pop_clip();
+            result.map_err(|e| format!("{}: {e}", widget.name()))?;
+            widget.clear_redraw();
+        }
+
+        if region.is_some() {
+            self.state.pop_clip();
+        }
+
+        self.spans = spans;
+
+        self.state.update()?;
+        match self.state.render() {
+            Ok(_) => {}
+            // Reconfigure the surface if lost
+            Err(wgpu::SurfaceError::Lost) => {
+                self.state.resize(self.state.width, self.state.height);
+                // the next frame has to be drawn from scratch
+                self.spans.clear();
+            }
+            // The system is out of memory, we should probably quit
+            Err(wgpu::SurfaceError::OutOfMemory) => return Ok(false),
+            // All other errors (Outdated, Timeout) should be resolved by the next frame
+            Err(e) => warn!("{:?}", e),
+        }
+
+        Ok(true)
     }
 }
 
+impl KeyGrab<'_> {
+    /// the keycodes with the modifiers and every combination of the locks,
+    /// since a grab only matches the exact modifiers
+    fn combinations(&self) -> impl Iterator<Item = (Keycode, u16)> + '_ {
+        let lock_sets = [
+            0,
+            u16::from(ModMask::LOCK),
+            self.locks & !u16::from(ModMask::LOCK),
+            self.locks,
+        ];
+
+        self.keycodes.iter().flat_map(move |&keycode| {
+            lock_sets
+                .into_iter()
+                .map(move |locks| (keycode, self.modifiers | locks))
+        })
+    }
+
+    fn matches(&self, event: &KeyPressEvent) -> bool {
+        // the pointer buttons are in the state too
+        let modifiers = u16::from(event.state) & 0xff & !self.locks;
+        self.keycodes.contains(&event.detail) && modifiers == self.modifiers
+    }
+}
+
+/// a `width` by `height` rect with its corners rounded by `radius`, as a row
+/// of pixels for every line of the corners and one rect for the middle
+fn rounded_rect_region(width: u32, height: u32, radius: u32) -> Vec<Rectangle> {
+    let radius = radius.min(width / 2).min(height / 2);
+    let r = radius as f32;
+
+    let mut top = Vec::new();
+    let mut bottom = Vec::new();
+    for y in 0..radius {
+        // how far the circle is from the edge, at the middle of the row
+        let dy = r - y as f32 - 0.5;
+        let inset = (r - (r * r - dy * dy).sqrt()).round() as u32;
+        let row = |y: u32| Rectangle {
+            x: inset as i16,
+            y: y as i16,
+            width: (width - 2 * inset) as u16,
+            height: 1,
+        };
+        top.push(row(y));
+        bottom.push(row(height - 1 - y));
+    }
+
+    let middle = Rectangle {
+        x: 0,
+        y: radius as i16,
+        width: width as u16,
+        height: (height - 2 * radius) as u16,
+    };
+
+    top.into_iter()
+        .chain(std::iter::once(middle))
+        .chain(bottom.into_iter().rev())
+        .collect()
+}
+
+/// sends the X events to the main loop until it's gone, or until the connection fails,
+/// events are logged first when `atom_cache` is given to resolve their atoms
+fn read_events(
+    connection: &XCBConnection,
+    event_sender: &Sender<Event>,
+    atom_cache: Option<&mdry::window::AtomCache>,
+) -> Result<(), ConnectionError> {
+    loop {
+        let mut event_option = Some(connection.wait_for_event()?);
+        while let Some(event) = event_option {
+            if let Some(atom_cache) = atom_cache {
+                trace::trace_event(connection, atom_cache, &event);
+            }
+
+            if event_sender.send(event).is_err() {
+                return Ok(());
+            }
+
+            event_option = connection.poll_for_event()?;
+        }
+    }
+}
+
+/// what changed since the last frame
+enum Damage {
+    /// anything could have changed, redraw the whole bar
+    Full,
+    /// only these widgets changed, by index
+    Widgets(Vec<usize>),
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_window(
     connection: &XCBConnection,
+    x: i16,
     width: u16,
     height: u16,
     screen_num: usize,
     display_scale: f32,
     bottom: bool,
+    transparent: bool,
 ) -> Result<Window, Error> {
     let screen = &connection.setup().roots[screen_num];
 
@@ -47,7 +927,28 @@ pub fn create_window(
 
     let window_id = connection.generate_id()?;
 
-    let create = CreateWindowAux::new().event_mask(
+    // a window with a different depth than its parent needs its own colormap
+    // and a border pixel, otherwise creating it fails with a BadMatch
+    let argb_visual = if transparent {
+        let visual = find_argb_visual(screen);
+        if visual.is_none() {
+            warn!("no 32-bit visual available, the bar won't be transparent");
+        }
+        visual
+    } else {
+        None
+    };
+
+    let (depth, visual, colormap) = match argb_visual {
+        Some(visual) => {
+            let colormap = connection.generate_id()?;
+            connection.create_colormap(ColormapAlloc::NONE, colormap, screen.root, visual)?;
+            (32, visual, Some(colormap))
+        }
+        None => (COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, None),
+    };
+
+    let mut create = CreateWindowAux::new().event_mask(
         EventMask::EXPOSURE
             | EventMask::STRUCTURE_NOTIFY
             | EventMask::VISIBILITY_CHANGE
@@ -60,94 +961,26 @@ pub fn create_window(
             | EventMask::PROPERTY_CHANGE,
     );
 
-    let (y, struts) = if bottom {
-        (
-            (screen.height_in_pixels - height) as i16,
-            // left, right, top, bottom, left_start_y, left_end_y,
-            // right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x,
-            // bottom_end_x
-            [
-                0,
-                0,
-                0,
-                height as u32,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                screen.width_in_pixels as u32,
-            ],
-        )
-    } else {
-        (
-            0,
-            // left, right, top, bottom, left_start_y, left_end_y,
-            // right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x,
-            // bottom_end_x
-            [
-                0,
-                0,
-                height as u32,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                screen.width_in_pixels as u32,
-                0,
-                0,
-            ],
-        )
-    };
+    if let Some(colormap) = colormap {
+        create = create.colormap(colormap).border_pixel(0);
+    }
+
+    let (y, struts) = dock_struts(screen.height_in_pixels, x, width, height, bottom);
 
     connection.create_window(
-        COPY_DEPTH_FROM_PARENT,
+        depth,
         window_id,
         screen.root,
-        0,
+        x,
         y,
         width,
         height,
         0,
         WindowClass::INPUT_OUTPUT,
-        COPY_FROM_PARENT,
+        visual,
         &create,
     )?;
 
-    connection
-        .change_property8(
-            PropMode::REPLACE,
-            window_id,
-            atoms._NET_WM_NAME,
-            AtomEnum::STRING,
-            b"lmao",
-        )?
-        .check()?;
-
-    connection
-        .change_property8(
-            PropMode::REPLACE,
-            window_id,
-            atoms.WM_NAME,
-            AtomEnum::STRING,
-            b"lmao",
-        )?
-        .check()?;
-
-    connection
-        .change_property8(
-            PropMode::REPLACE,
-            window_id,
-            x11rb::protocol::xproto::Atom::from(x11rb::protocol::xproto::AtomEnum::WM_CLASS),
-            AtomEnum::STRING,
-            b"lmao",
-        )?
-        .check()?;
-
     connection
         .change_property32(
             PropMode::REPLACE,
@@ -174,34 +1007,116 @@ pub fn create_window(
             window_id,
             atoms._NET_WM_STATE,
             AtomEnum::ATOM,
-            &[atoms._NET_WM_STATE_STICKY, atoms._NET_WM_STATE_ABOVE],
+            // some window managers already keep docks out of task switchers,
+            // the skip hints are for the ones that don't
+            &[
+                atoms._NET_WM_STATE_STICKY,
+                atoms._NET_WM_STATE_ABOVE,
+                atoms._NET_WM_STATE_SKIP_TASKBAR,
+                atoms._NET_WM_STATE_SKIP_PAGER,
+            ],
         )?
         .check()?;
 
+    set_struts(connection, window_id, &atoms, &struts)?;
+
+    let window = Window {
+        xid: window_id,
+        connection,
+        screen_num,
+        width: width as u32,
+        height: height as u32,
+        atoms,
+        display_scale,
+        x: x.into(),
+        y: y.into(),
+        window_type: WindowType::Dock { bottom, struts },
+        transparent: argb_visual.is_some(),
+        cursors: Default::default(),
+        atom_cache: Default::default(),
+    };
+
+    // set before mapping, window manager rules are usually matched on map
+    window.set_title(WINDOW_NAME)?;
+    window.set_class(WINDOW_NAME, WINDOW_NAME)?;
+
+    connection.map_window(window_id)?;
+
+    connection.flush()?;
+
+    Ok(window)
+}
+
+/// the y of a dock at the top or bottom edge of the screen, and the `_NET_WM_STRUT_PARTIAL`
+/// that reserves the space under it, only along the x range the dock covers
+pub fn dock_struts(
+    screen_height: u16,
+    x: i16,
+    width: u16,
+    height: u16,
+    bottom: bool,
+) -> (i16, [u32; 12]) {
+    // the end is inclusive
+    let start_x = x.max(0) as u32;
+    let end_x = start_x + (width as u32).saturating_sub(1);
+
+    if bottom {
+        (
+            (screen_height - height) as i16,
+            // left, right, top, bottom, left_start_y, left_end_y,
+            // right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x,
+            // bottom_end_x
+            [0, 0, 0, height as u32, 0, 0, 0, 0, 0, 0, start_x, end_x],
+        )
+    } else {
+        (
+            0,
+            // left, right, top, bottom, left_start_y, left_end_y,
+            // right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x,
+            // bottom_end_x
+            [0, 0, height as u32, 0, 0, 0, 0, 0, start_x, end_x, 0, 0],
+        )
+    }
+}
+
+/// sets the partial strut, and the full-edge strut derived from it
+pub(crate) fn set_struts(
+    connection: &XCBConnection,
+    window_id: Xwindow,
+    atoms: &mdry::window::Atoms,
+    struts: &[u32; 12],
+) -> Result<(), Error> {
     connection
         .change_property32(
             PropMode::REPLACE,
             window_id,
             atoms._NET_WM_STRUT_PARTIAL,
             AtomEnum::CARDINAL,
-            &struts,
+            struts,
         )?
         .check()?;
 
-    connection.map_window(window_id)?;
+    // for window managers that only know the older, full-edge strut
+    connection
+        .change_property32(
+            PropMode::REPLACE,
+            window_id,
+            atoms._NET_WM_STRUT,
+            AtomEnum::CARDINAL,
+            &struts[..4],
+        )?
+        .check()?;
 
-    connection.flush()?;
+    Ok(())
+}
 
-    Ok(Window {
-        xid: window_id,
-        connection,
-        screen_num,
-        width: width as u32,
-        height: height as u32,
-        atoms,
-        display_scale,
-        x: 0,
-        y: y.into(),
-        window_type: WindowType::Dock { bottom, struts },
-    })
+/// finds a 32-bit TrueColor visual, which has an alpha channel
+fn find_argb_visual(screen: &Screen) -> Option<Visualid> {
+    screen
+        .allowed_depths
+        .iter()
+        .filter(|depth| depth.depth == 32)
+        .flat_map(|depth| depth.visuals.iter())
+        .find(|visual| visual.class == VisualClass::TRUE_COLOR)
+        .map(|visual| visual.visual_id)
 }
@@ -1,17 +1,24 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crossbeam::channel::{Receiver, Sender};
+
+use log::warn;
 use mdry::{color::Color, window::Window};
 use shareet::{
+    config::{Config, Registry, WidgetContext},
     create_window,
-    widgets::{cpu_usage::CPUUsage, pager::Pager, sys_time::SysTime, sys_tray::SysTray},
-    Bar, Error,
+    widgets::{
+        cpu_usage::CPUUsage,
+        pager::{Pager, PagerLabelMode},
+        sys_time::{self, SysTime},
+        sys_tray::SysTray,
+    },
+    Bar, Error, Exit,
 };
 use x11rb::{
     connection::Connection,
-    protocol::{
-        xproto::{ChangeWindowAttributesAux, ConnectionExt, EventMask},
-        Event,
-    },
+    errors::{ConnectionError, ReplyError, ReplyOrIdError},
     xcb_ffi::XCBConnection,
 };
 
@@ -19,179 +26,235 @@ use x11rb::{
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
+/// how long to wait before reconnecting to the X server the first time, doubled after every
+/// failed attempt up to [`MAX_RECONNECT_DELAY`]
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
 fn main() -> Result<(), Error> {
+    // quiet unless something's wrong, `RUST_LOG=shareet=debug` shows what the widgets are doing,
+    // traced events are only logged with `SHAREET_TRACE` so their target can be on by default
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("warn,shareet::events=info"),
+    )
+    .init();
+
     #[cfg(feature = "profiling")]
     let profiler = dhat::Profiler::new_heap();
     #[cfg(feature = "profiling")]
-    println!("Profiling...");
+    log::info!("Profiling...");
 
-    #[cfg(feature = "profiling")]
-    let (sender, receiver) = std::sync::mpsc::channel();
+    let config = match Config::load() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            warn!("{e}, using the default config");
+            Config::default()
+        }
+    };
+
+    // SIGINT, SIGTERM and SIGHUP, so the widgets get to clean up
+    // when stopped by a service manager or on logout,
+    // the handler can only be set once so it stops whichever bar is running,
+    // or the reconnecting when none is
+    let running: Arc<Mutex<Option<Sender<()>>>> = Arc::default();
+    let (stop_sender, stop_receiver) = crossbeam::channel::bounded(1);
+    {
+        let running = running.clone();
+        ctrlc::set_handler(move || {
+            let _ = stop_sender.try_send(());
+            if let Some(shutdown_sender) = running.lock().unwrap().as_ref() {
+                let _ = shutdown_sender.try_send(());
+            }
+        })
+        .expect("Error setting the signal handler");
+    }
+
+    let mut reconnecting = false;
+    let mut delay = MIN_RECONNECT_DELAY;
+
+    loop {
+        let (connection, screen_num) = match XCBConnection::connect(None) {
+            Ok(connected) => connected,
+            // the server is probably still starting up
+            Err(e) if reconnecting => {
+                warn!("could not reconnect to the X server: {e}, retrying in {delay:?}");
+                if stop_receiver.recv_timeout(delay).is_ok() {
+                    break;
+                }
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        delay = MIN_RECONNECT_DELAY;
+
+        match run_bar(
+            Arc::new(connection),
+            screen_num,
+            &config,
+            &running,
+            &stop_receiver,
+        )? {
+            Exit::Shutdown => break,
+            Exit::ConnectionLost => {
+                warn!("reconnecting to the X server");
+                reconnecting = true;
+            }
+        }
+
+        if stop_receiver.try_recv().is_ok() {
+            break;
+        }
+    }
 
     #[cfg(feature = "profiling")]
-    ctrlc::set_handler(move || sender.send(()).expect("Could not send signal on channel."))
-        .expect("Error setting Ctrl-C handler");
+    drop(profiler);
+
+    Ok(())
+}
 
-    let (connection, screen_num) = XCBConnection::connect(None)?;
+/// makes the bar with a fresh window and widgets on `connection`, and runs it
+///
+/// errors from a connection that went away while the bar was being set up or redrawn
+/// count as losing the connection, so the bar is made again after reconnecting
+fn run_bar(
+    connection: Arc<XCBConnection>,
+    screen_num: usize,
+    config: &Config,
+    running: &Mutex<Option<Sender<()>>>,
+    stop_receiver: &Receiver<()>,
+) -> Result<Exit, Error> {
+    let result = make_and_run_bar(
+        connection.clone(),
+        screen_num,
+        config,
+        running,
+        stop_receiver,
+    );
+    *running.lock().unwrap() = None;
+
+    match result {
+        Err(e) if is_connection_error(&*e) || connection.has_error().is_some() => {
+            warn!("lost the connection to the X server: {e}");
+            Ok(Exit::ConnectionLost)
+        }
+        result => result,
+    }
+}
 
-    let connection = Arc::new(connection);
+fn is_connection_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.is::<ConnectionError>()
+        || matches!(
+            error.downcast_ref::<ReplyError>(),
+            Some(ReplyError::ConnectionError(_))
+        )
+        || matches!(
+            error.downcast_ref::<ReplyOrIdError>(),
+            Some(ReplyOrIdError::ConnectionError(_))
+        )
+}
 
+fn make_and_run_bar(
+    connection: Arc<XCBConnection>,
+    screen_num: usize,
+    config: &Config,
+    running: &Mutex<Option<Sender<()>>>,
+    stop_receiver: &Receiver<()>,
+) -> Result<Exit, Error> {
     let screen = &connection.setup().roots[screen_num];
 
     let width = screen.width_in_pixels;
-    let height = 35;
+    let height = config.bar.height;
 
     // let width = 100;
     // let height = 100;
 
     let display_scale = 1.;
 
-    let window = create_window(&connection, width, height, screen_num, display_scale, false)?;
+    let window = create_window(
+        &connection,
+        0,
+        width,
+        height,
+        screen_num,
+        display_scale,
+        config.bar.bottom,
+        false,
+    )?;
 
     let mut bar = pollster::block_on(run(window));
 
-    connection.flush()?;
-
-    let change = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
-
-    connection
-        .change_window_attributes(screen.root, &change)?
-        .check()?;
-
-    let foreground = Color::rgb(191, 189, 182);
-    let background = Color::rgb(26, 29, 36);
+    // set before the widgets are built, which can take a while, so a signal in the meantime
+    // still stops the bar, one that came before the bar existed only reached `stop_receiver`
+    let shutdown_sender = bar.shutdown_sender();
+    *running.lock().unwrap() = Some(shutdown_sender.clone());
+    if !stop_receiver.is_empty() {
+        let _ = shutdown_sender.try_send(());
+    }
 
-    bar.widgets.push(Box::new(Pager::new(
-        &connection,
-        glyphon::Metrics::new(bar.state.height as f32, bar.state.height as f32),
-        foreground,
-        Color::rgb(233, 86, 120),
-        5.,
-    )?));
+    connection.flush()?;
 
-    bar.widgets.push(Box::new(SysTray::new(
-        &connection,
-        screen_num,
-        bar.state.width,
-        bar.state.height,
-        20,
-        5,
-        background,
-    )?));
-
-    bar.widgets
-        .push(Box::new(SysTime::new(bar.state.height as f32, foreground)));
-
-    // XXX: broken
-    // bar.widgets
-    //     .push(Box::new(CPUUsage::new(bar.state.height as f32, foreground)));
-
-    let (event_sender, event_receiver) = crossbeam::channel::unbounded::<Event>();
-    let (redraw_sender, redraw_receiver) = crossbeam::channel::unbounded::<()>();
-
-    for widget in bar.widgets.iter_mut() {
-        widget
-            .setup(
-                &mut bar.state,
-                &connection,
-                screen_num,
-                redraw_sender.clone(),
-            )
-            .unwrap();
+    let foreground = config.bar.foreground()?;
+    let background = config.bar.background()?;
+    bar.background = background;
+    bar.set_margin(config.bar.margin);
+    bar.set_spacing(config.bar.spacing);
+    bar.set_pseudo_transparent(config.bar.pseudo_transparent);
+    if config.bar.corner_radius > 0 {
+        bar.set_corner_radius(config.bar.corner_radius)?;
     }
 
-    {
-        let connection = connection.clone();
-        std::thread::spawn(move || {
-            loop {
-                #[cfg(feature = "profiling")]
-                match receiver.try_recv() {
-                    Ok(_) => {
-                        drop(profiler);
-                        std::process::exit(0);
-                    }
-                    Err(_) => {}
-                }
+    if config.widgets.is_empty() {
+        bar.widgets.push(Box::new(Pager::new(
+            &connection,
+            glyphon::Metrics::new(bar.state.height as f32, bar.state.height as f32),
+            foreground,
+            Color::rgb(233, 86, 120),
+            5.,
+            PagerLabelMode::Names,
+        )?));
 
-                let event = connection.wait_for_event().unwrap();
-                let mut event_option = Some(event);
-                while let Some(event) = event_option {
-                    // if matches!(event, Event::PropertyNotify(_)) {
-                    //     println!("got event: {event:#?}");
-                    // }
+        bar.widgets.push(Box::new(SysTray::new(
+            &connection,
+            screen_num,
+            bar.state.width,
+            bar.state.height,
+            20,
+            5,
+            background,
+        )?));
 
-                    event_sender.send(event).unwrap();
+        bar.widgets.push(Box::new(
+            SysTime::new(
+                bar.state.height as f32,
+                foreground,
+                sys_time::DEFAULT_FORMAT,
+            )?
+            .with_calendar(background, Color::rgb(233, 86, 120)),
+        ));
 
-                    event_option = connection.poll_for_event().unwrap();
-                }
-            }
-        });
-    }
-    loop {
-        crossbeam::select! {
-            recv(event_receiver) -> event => {
-                if let Ok(event) = event {
-
-                match event {
-                    Event::ClientMessage(event) => {
-                        if event.data.as_data32()[0] == bar.state.window.atoms.WM_DELETE_WINDOW {
-                            return Ok(());
-                        }
-                    }
-                    Event::PropertyNotify(event) if event.window == screen.root => {
-                        redraw_sender.send(()).unwrap();
-                    }
-                    Event::Expose(_) => redraw_sender.send(())?,
-                    Event::LeaveNotify(_) => redraw_sender.send(())?,
-                    Event::EnterNotify(_) => redraw_sender.send(())?,
-                    Event::ConfigureNotify(_) => redraw_sender.send(())?,
-                    _ => {}
-                }
+        // XXX: broken
+        // bar.widgets
+        //     .push(Box::new(CPUUsage::new(bar.state.height as f32, foreground)));
+    } else {
+        let context = WidgetContext {
+            connection: &connection,
+            screen_num,
+            bar_width: bar.state.width,
+            bar_height: bar.state.height,
+            foreground,
+            background,
+        };
 
-                for widget in bar.widgets.iter_mut() {
-                    if let Err(e) =
-                        widget.on_event(&connection, screen_num, &mut bar.state, event.clone(), redraw_sender.clone())
-                    {
-                        eprintln!("widget error: {e}");
-                    }
-                }
-                }
-            },
-            recv(redraw_receiver) -> _ => {
-                let width = bar.state.width as f32;
-                bar.state.clear_background(background);
-                let mut roffset = 0.;
-                let mut loffset = 0.;
-                for widget in bar.widgets.iter_mut() {
-                    let size = widget.size(&mut bar.state);
-                    match widget.alignment() {
-                        shareet::widgets::Alignment::Left => {
-                            widget.draw(&connection, screen_num, &mut bar.state, loffset)?;
-                            loffset += size;
-                        },
-                        shareet::widgets::Alignment::Right => {
-                            widget.draw(&connection, screen_num, &mut bar.state, width - roffset - size)?;
-                            roffset += size;
-                        },
-                    }
-                }
-                bar.state.update()?;
-                match bar.state.render() {
-                    Ok(_) => {}
-                    // Reconfigure the surface if lost
-                    Err(wgpu::SurfaceError::Lost) => {
-                        bar.state.resize(bar.state.width, bar.state.height)
-                    }
-                    // The system is out of memory, we should probably quit
-                    Err(wgpu::SurfaceError::OutOfMemory) => return Ok(()),
-                    // All other errors (Outdated, Timeout) should be resolved by the next frame
-                    Err(e) => eprintln!("{:?}", e),
-                }
-            }
-        }
+        bar.widgets = Registry::default().build_all(&config.widgets, &context)?;
     }
+
+    bar.run(connection.clone())
 }
 
 async fn run<'a>(window: Window<'a>) -> Bar<'a> {
-    Bar::new(window).await
+    // 4x MSAA to smooth out circles and diagonal edges,
+    // and vsync since a tearing bar looks broken
+    Bar::new(window, 4, wgpu::PresentMode::Fifo).await
 }
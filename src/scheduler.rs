@@ -0,0 +1,149 @@
+use std::{
+    cell::Cell,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, Timelike};
+use crossbeam::channel::{Receiver, Sender};
+
+/// Identifies a timer registered with [`Scheduler::register`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerToken(u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// fires every `Duration`, starting one `Duration` after it's registered
+    Interval(Duration),
+    /// fires right when the wall clock minute changes
+    EveryMinute,
+}
+
+impl Schedule {
+    fn next_deadline(&self, now: Instant) -> Instant {
+        match self {
+            Schedule::Interval(interval) => now + *interval,
+            Schedule::EveryMinute => {
+                let now_local = Local::now();
+                let elapsed = Duration::new(
+                    now_local.second() as u64,
+                    now_local.nanosecond() % 1_000_000_000,
+                );
+                now + Duration::from_secs(60).saturating_sub(elapsed)
+            }
+        }
+    }
+}
+
+enum Command {
+    Register(TimerToken, Schedule),
+    Cancel(TimerToken),
+    Shutdown,
+}
+
+/// Runs every widget timer on a single thread
+///
+/// fired timers are sent to the receiver returned by [`Scheduler::new`],
+/// and dispatched to [`Widget::on_timer`](crate::widgets::Widget::on_timer) by the main loop
+pub struct Scheduler {
+    commands: Sender<Command>,
+    next_token: Cell<u64>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    pub fn new() -> (Self, Receiver<TimerToken>) {
+        let (command_sender, command_receiver) = crossbeam::channel::unbounded();
+        let (fired_sender, fired_receiver) = crossbeam::channel::unbounded();
+
+        let thread = std::thread::spawn(move || run(command_receiver, fired_sender));
+
+        (
+            Self {
+                commands: command_sender,
+                next_token: Cell::new(0),
+                thread: Some(thread),
+            },
+            fired_receiver,
+        )
+    }
+
+    pub fn register(&self, schedule: Schedule) -> TimerToken {
+        let token = TimerToken(self.next_token.get());
+        self.next_token.set(token.0 + 1);
+
+        let _ = self.commands.send(Command::Register(token, schedule));
+
+        token
+    }
+
+    pub fn cancel(&self, token: TimerToken) {
+        let _ = self.commands.send(Command::Cancel(token));
+    }
+
+    /// stops the timer thread and waits for it to exit
+    pub fn shutdown(&mut self) {
+        let _ = self.commands.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run(commands: Receiver<Command>, fired: Sender<TimerToken>) {
+    let mut schedules: HashMap<TimerToken, Schedule> = HashMap::new();
+    let mut deadlines: BinaryHeap<Reverse<(Instant, TimerToken)>> = BinaryHeap::new();
+
+    loop {
+        let command = match deadlines.peek() {
+            Some(Reverse((deadline, _))) => match commands.recv_deadline(*deadline) {
+                Ok(command) => Some(command),
+                Err(crossbeam::channel::RecvTimeoutError::Timeout) => None,
+                Err(crossbeam::channel::RecvTimeoutError::Disconnected) => return,
+            },
+            None => match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            },
+        };
+
+        match command {
+            Some(Command::Register(token, schedule)) => {
+                schedules.insert(token, schedule);
+                deadlines.push(Reverse((schedule.next_deadline(Instant::now()), token)));
+            }
+            Some(Command::Cancel(token)) => {
+                // the deadline is dropped once it's popped
+                schedules.remove(&token);
+            }
+            Some(Command::Shutdown) => return,
+            None => {
+                let now = Instant::now();
+                while let Some(Reverse((deadline, token))) = deadlines.peek().copied() {
+                    if deadline > now {
+                        break;
+                    }
+                    deadlines.pop();
+
+                    let Some(schedule) = schedules.get(&token) else {
+                        continue;
+                    };
+
+                    if fired.send(token).is_err() {
+                        return;
+                    }
+
+                    deadlines.push(Reverse((schedule.next_deadline(now), token)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use log::info;
+use mdry::window::AtomCache;
+use x11rb::protocol::xproto::Atom;
+use x11rb::protocol::Event;
+use x11rb::xcb_ffi::XCBConnection;
+
+/// the log target for traced events, shown by default so `SHAREET_TRACE` alone is enough
+pub const TARGET: &str = "shareet::events";
+
+/// logs `event` with its window ids in hex and its atoms resolved to their names
+pub fn trace_event(connection: &XCBConnection, atom_cache: &AtomCache, event: &Event) {
+    let name = |atom: Atom| atom_name(connection, atom_cache, atom);
+
+    match event {
+        Event::PropertyNotify(event) => info!(
+            target: TARGET,
+            "PropertyNotify window={:#x} atom={} state={:?}",
+            event.window,
+            name(event.atom),
+            event.state
+        ),
+        Event::ClientMessage(event) => info!(
+            target: TARGET,
+            "ClientMessage window={:#x} type={} format={} data={:?}",
+            event.window,
+            name(event.type_),
+            event.format,
+            event.data
+        ),
+        Event::SelectionClear(event) => info!(
+            target: TARGET,
+            "SelectionClear owner={:#x} selection={}",
+            event.owner,
+            name(event.selection)
+        ),
+        Event::SelectionRequest(event) => info!(
+            target: TARGET,
+            "SelectionRequest owner={:#x} requestor={:#x} selection={} target={} property={}",
+            event.owner,
+            event.requestor,
+            name(event.selection),
+            name(event.target),
+            name(event.property)
+        ),
+        Event::SelectionNotify(event) => info!(
+            target: TARGET,
+            "SelectionNotify requestor={:#x} selection={} target={} property={}",
+            event.requestor,
+            name(event.selection),
+            name(event.target),
+            name(event.property)
+        ),
+        Event::MapNotify(event) => info!(
+            target: TARGET,
+            "MapNotify event={:#x} window={:#x}",
+            event.event,
+            event.window
+        ),
+        Event::UnmapNotify(event) => info!(
+            target: TARGET,
+            "UnmapNotify event={:#x} window={:#x}",
+            event.event,
+            event.window
+        ),
+        Event::DestroyNotify(event) => info!(
+            target: TARGET,
+            "DestroyNotify event={:#x} window={:#x}",
+            event.event,
+            event.window
+        ),
+        Event::ReparentNotify(event) => info!(
+            target: TARGET,
+            "ReparentNotify window={:#x} parent={:#x}",
+            event.window,
+            event.parent
+        ),
+        Event::ConfigureNotify(event) => info!(
+            target: TARGET,
+            "ConfigureNotify window={:#x} {}x{}+{}+{}",
+            event.window,
+            event.width,
+            event.height,
+            event.x,
+            event.y
+        ),
+        event => info!(target: TARGET, "{event:?}"),
+    }
+}
+
+/// the atom's name, or its number if the server doesn't know it
+fn atom_name(connection: &XCBConnection, atom_cache: &AtomCache, atom: Atom) -> String {
+    if atom == x11rb::NONE {
+        return "None".to_string();
+    }
+
+    atom_cache
+        .name(connection, atom)
+        .unwrap_or_else(|_| atom.to_string())
+}
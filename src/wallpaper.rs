@@ -0,0 +1,66 @@
+use mdry::image::RgbaImage;
+use mdry::window::Atoms;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, ImageFormat, ImageOrder};
+use x11rb::xcb_ffi::XCBConnection;
+
+use crate::Error;
+
+/// reads the part of the desktop wallpaper under the given rect of the screen,
+/// from the root pixmap wallpaper setters like feh and nitrogen leave in
+/// `_XROOTPMAP_ID` or `ESETROOT_PMAP_ID`, `None` if neither is set
+pub fn read_wallpaper(
+    connection: &XCBConnection,
+    screen_num: usize,
+    atoms: &Atoms,
+    (x, y): (i16, i16),
+    (width, height): (u16, u16),
+) -> Result<Option<RgbaImage>, Error> {
+    let root = connection.setup().roots[screen_num].root;
+
+    let mut pixmap = None;
+    for atom in [atoms._XROOTPMAP_ID, atoms.ESETROOT_PMAP_ID] {
+        pixmap = connection
+            .get_property(false, root, atom, AtomEnum::PIXMAP, 0, 1)?
+            .reply()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .filter(|pixmap| *pixmap != x11rb::NONE);
+
+        if pixmap.is_some() {
+            break;
+        }
+    }
+
+    let Some(pixmap) = pixmap else {
+        return Ok(None);
+    };
+
+    let image = connection
+        .get_image(ImageFormat::Z_PIXMAP, pixmap, x, y, width, height, !0)?
+        .reply()?;
+
+    let setup = connection.setup();
+    let bits_per_pixel = setup
+        .pixmap_formats
+        .iter()
+        .find(|format| format.depth == image.depth)
+        .map(|format| format.bits_per_pixel);
+
+    // 24 and 32 bit true color, which is what every wallpaper setter uses in practice
+    if image.depth < 24 || bits_per_pixel != Some(32) {
+        return Err(format!("unsupported wallpaper depth {}", image.depth).into());
+    }
+
+    let msb_first = setup.image_byte_order == ImageOrder::MSB_FIRST;
+    let pixels = image
+        .data
+        .chunks_exact(4)
+        .flat_map(|pixel| match msb_first {
+            true => [pixel[1], pixel[2], pixel[3], 255],
+            false => [pixel[2], pixel[1], pixel[0], 255],
+        })
+        .collect();
+
+    Ok(RgbaImage::from_raw(width as u32, height as u32, pixels))
+}
@@ -0,0 +1,261 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+use log::warn;
+use mdry::{color::Color, State};
+use x11rb::{protocol::Event, xcb_ffi::XCBConnection};
+
+use super::{Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::{Schedule, Scheduler, TimerToken};
+
+const SCROLL_UP: u8 = 4;
+const SCROLL_DOWN: u8 = 5;
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+pub const DEFAULT_ICON: &str = "☀";
+
+/// The screen brightness from `/sys/class/backlight`, scrolling over it changes the brightness
+///
+/// changing it needs write access to the device's `brightness` file, which is usually
+/// only writable by root unless a udev rule gives it to a group like `video`,
+/// without it the widget only shows the brightness
+pub struct Brightness {
+    device: PathBuf,
+    font_size: f32,
+    color: Color,
+    icon: String,
+    /// how much a scroll step changes the brightness, in percent
+    step: u32,
+    brightness: u32,
+    max_brightness: u32,
+    /// cleared after the first failed write, so it's only reported once
+    writable: bool,
+    timer: Option<TimerToken>,
+    /// where the widget was last drawn, to hit test scrolls
+    offset: f32,
+    width: f32,
+    alignment: Alignment,
+}
+
+impl Brightness {
+    /// `device` is the name of a directory in `/sys/class/backlight`, like "intel_backlight",
+    /// or `None` to use the first one
+    pub fn new(
+        device: Option<&str>,
+        font_size: f32,
+        color: Color,
+        step: u32,
+    ) -> Result<Self, crate::Error> {
+        let device = match device {
+            Some(device) => Path::new(BACKLIGHT_DIR).join(device),
+            None => first_device()?,
+        };
+
+        let max_brightness = read_value(&device.join("max_brightness"))?;
+        let brightness = read_value(&device.join("brightness"))?;
+
+        Ok(Self {
+            device,
+            font_size,
+            color,
+            icon: DEFAULT_ICON.to_string(),
+            step,
+            brightness,
+            max_brightness,
+            writable: true,
+            timer: None,
+            offset: 0.,
+            width: 0.,
+            alignment: Alignment::Right,
+        })
+    }
+
+    /// `device`, `font_size`, `color`, `icon` and `step`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let device = config::get_str(table, "device", "")?;
+        let font_size = config::get_f32(table, "font_size", context.bar_height as f32)?;
+
+        Ok(Self::new(
+            (!device.is_empty()).then_some(device),
+            font_size,
+            config::get_color(table, "color", context.foreground)?,
+            config::get_u32(table, "step", 5)?,
+        )?
+        .with_icon(config::get_str(table, "icon", DEFAULT_ICON)?))
+    }
+
+    pub fn with_icon(mut self, icon: &str) -> Self {
+        self.icon = icon.to_string();
+        self
+    }
+
+    fn percent(&self) -> u32 {
+        if self.max_brightness == 0 {
+            return 0;
+        }
+
+        (self.brightness as f32 / self.max_brightness as f32 * 100.).round() as u32
+    }
+
+    fn text(&self) -> String {
+        format!("{} {}%", self.icon, self.percent())
+    }
+
+    /// returns whether the brightness changed
+    fn update(&mut self) -> bool {
+        match read_value(&self.device.join("brightness")) {
+            Ok(brightness) => {
+                let changed = brightness != self.brightness;
+                self.brightness = brightness;
+                changed
+            }
+            Err(e) => {
+                warn!("could not read the brightness: {e}");
+                false
+            }
+        }
+    }
+
+    /// changes the brightness by `step` percent, up or down
+    fn adjust(&mut self, up: bool) {
+        if !self.writable {
+            return;
+        }
+
+        let step = (self.max_brightness * self.step / 100).max(1);
+        let brightness = if up {
+            self.brightness.saturating_add(step)
+        } else {
+            self.brightness.saturating_sub(step)
+        };
+        // 0 turns some backlights off completely
+        let brightness = brightness.clamp(1, self.max_brightness.max(1));
+
+        match std::fs::write(self.device.join("brightness"), brightness.to_string()) {
+            Ok(()) => self.brightness = brightness,
+            Err(e) => {
+                if e.kind() == ErrorKind::PermissionDenied {
+                    warn!(
+                        "no permission to change the brightness of {}, only showing it",
+                        self.device.display()
+                    );
+                } else {
+                    warn!("could not change the brightness: {e}");
+                }
+                self.writable = false;
+            }
+        }
+    }
+}
+
+fn first_device() -> Result<PathBuf, crate::Error> {
+    let mut devices = std::fs::read_dir(BACKLIGHT_DIR)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    devices.sort();
+
+    devices
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("no backlight devices in {BACKLIGHT_DIR}").into())
+}
+
+fn read_value(path: &Path) -> Result<u32, crate::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+
+    Ok(content.trim().parse()?)
+}
+
+impl Widget for Brightness {
+    fn name(&self) -> &str {
+        "brightness"
+    }
+
+    fn setup(
+        &mut self,
+        _state: &mut State,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        _redraw_sender: Sender<()>,
+        scheduler: &Scheduler,
+    ) -> Result<(), crate::Error> {
+        // sysfs doesn't support inotify, and brightness keys change it behind our back
+        self.timer = Some(scheduler.register(Schedule::Interval(Duration::from_secs(1))));
+
+        Ok(())
+    }
+
+    fn on_event(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        state: &mut State,
+        event: Event,
+        redraw_sender: Sender<()>,
+    ) -> Result<(), crate::Error> {
+        let Event::ButtonPress(event) = event else {
+            return Ok(());
+        };
+        // presses on other windows, like tray icons or the calendar, are in their coordinates
+        if event.event != state.window().xid {
+            return Ok(());
+        }
+
+        let event_x = event.event_x as f32;
+        if event_x < self.offset || event_x > self.offset + self.width {
+            return Ok(());
+        }
+
+        match event.detail {
+            SCROLL_UP => self.adjust(true),
+            SCROLL_DOWN => self.adjust(false),
+            _ => return Ok(()),
+        }
+
+        redraw_sender.send(())?;
+
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::BUTTON
+    }
+
+    fn draw(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        state: &mut State,
+        offset: f32,
+    ) -> Result<(), crate::Error> {
+        self.offset = offset;
+        state.draw_text_absolute_cached(&self.text(), 0., 0., self.color, self.font_size);
+
+        Ok(())
+    }
+
+    fn on_timer(&mut self, token: TimerToken, _state: &mut State) -> bool {
+        self.timer == Some(token) && self.update()
+    }
+
+    fn size(&mut self, state: &mut State) -> f32 {
+        let metrics = glyphon::Metrics::new(self.font_size, self.font_size);
+        self.width = state.measure_text(&self.text(), metrics).0;
+        self.width
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+}
@@ -0,0 +1,301 @@
+use chrono::{Datelike, Local, NaiveDate};
+use log::warn;
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{
+            ConfigureWindowAux, ConnectionExt, CreateGCAux, CreateWindowAux, EventMask, GrabMode,
+            GrabStatus, ImageFormat, Window, WindowClass,
+        },
+        Event,
+    },
+    xcb_ffi::XCBConnection,
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME,
+};
+
+use mdry::{
+    color::Color,
+    shapes::{Rect, Shape},
+    window::WindowType,
+    State,
+};
+
+const FONT_SIZE: f32 = 14.;
+const CELL_WIDTH: u32 = 32;
+const CELL_HEIGHT: u32 = 24;
+/// the month and the weekdays, then enough weeks for any month
+const ROWS: u32 = 2 + 6;
+
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// A month calendar in a popup window of its own, under or over the bar
+///
+/// it's drawn offscreen with mdry and copied into the window, so it doesn't
+/// need a second surface, and it closes on the next click anywhere
+pub struct CalendarPopup {
+    background: Color,
+    foreground: Color,
+    highlight: Color,
+    /// created the first time the calendar is opened
+    window: Option<Window>,
+    /// created the first time the calendar is opened, and kept for the next times
+    state: Option<State<'static>>,
+    /// the rendered calendar, copied into the window again when it's exposed
+    pixels: Vec<u8>,
+    open: bool,
+}
+
+impl CalendarPopup {
+    pub fn new(background: Color, foreground: Color, highlight: Color) -> Self {
+        Self {
+            background,
+            foreground,
+            highlight,
+            window: None,
+            state: None,
+            pixels: Vec::new(),
+            open: false,
+        }
+    }
+
+    fn size() -> (u32, u32) {
+        (7 * CELL_WIDTH, ROWS * CELL_HEIGHT)
+    }
+
+    /// shows the current month centered under `center_x` of the bar,
+    /// or over the bar if it's at the bottom of the screen
+    pub fn open(
+        &mut self,
+        connection: &XCBConnection,
+        bar: &State,
+        center_x: f32,
+    ) -> Result<(), crate::Error> {
+        let (width, height) = Self::size();
+        let bar_window = bar.window();
+        let screen = &connection.setup().roots[bar_window.screen_num];
+
+        let x = (bar_window.x + center_x as i32 - width as i32 / 2)
+            .clamp(0, (screen.width_in_pixels as i32 - width as i32).max(0));
+        let y = match bar_window.window_type {
+            WindowType::Dock { bottom: true, .. } => bar_window.y - height as i32,
+            _ => bar_window.y + bar_window.height as i32,
+        };
+
+        self.render(Local::now().date_naive())?;
+
+        let window = match self.window {
+            Some(window) => window,
+            None => {
+                let window = connection.generate_id()?;
+                // override redirect, so the window manager doesn't frame or place it
+                let create = CreateWindowAux::new()
+                    .override_redirect(1)
+                    .background_pixel(self.background.to_argb_u32())
+                    .event_mask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS);
+                connection
+                    .create_window(
+                        COPY_DEPTH_FROM_PARENT,
+                        window,
+                        screen.root,
+                        x as i16,
+                        y as i16,
+                        width as u16,
+                        height as u16,
+                        0,
+                        WindowClass::INPUT_OUTPUT,
+                        COPY_FROM_PARENT,
+                        &create,
+                    )?
+                    .check()?;
+                *self.window.insert(window)
+            }
+        };
+
+        connection.configure_window(window, &ConfigureWindowAux::new().x(x).y(y))?;
+        connection.map_window(window)?;
+
+        // every click goes to the popup while it's open, so clicks outside of it close it
+        let grab = connection
+            .grab_pointer(
+                false,
+                window,
+                EventMask::BUTTON_PRESS,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                CURRENT_TIME,
+            )?
+            .reply()?;
+        if grab.status != GrabStatus::SUCCESS {
+            warn!(
+                "could not grab the pointer for the calendar: {:?}",
+                grab.status
+            );
+        }
+
+        connection.flush()?;
+        self.open = true;
+
+        Ok(())
+    }
+
+    pub fn close(&mut self, connection: &XCBConnection) -> Result<(), crate::Error> {
+        if let Some(window) = self.window.filter(|_| self.open) {
+            connection.ungrab_pointer(CURRENT_TIME)?;
+            connection.unmap_window(window)?;
+            connection.flush()?;
+        }
+        self.open = false;
+
+        Ok(())
+    }
+
+    pub fn destroy(&mut self, connection: &XCBConnection) -> Result<(), crate::Error> {
+        self.close(connection)?;
+        if let Some(window) = self.window.take() {
+            connection.destroy_window(window)?;
+        }
+
+        Ok(())
+    }
+
+    /// repaints the popup when it's exposed, and closes it on clicks outside of it,
+    /// returns whether the event was for the popup
+    pub fn handle_event(
+        &mut self,
+        connection: &XCBConnection,
+        event: &Event,
+    ) -> Result<bool, crate::Error> {
+        let Some(window) = self.window else {
+            return Ok(false);
+        };
+
+        match event {
+            Event::Expose(event) if event.window == window => {
+                if event.count == 0 {
+                    self.paint(connection, window)?;
+                }
+                Ok(true)
+            }
+            Event::ButtonPress(event) if event.event == window => {
+                let (width, height) = Self::size();
+                let inside = event.event_x >= 0
+                    && event.event_y >= 0
+                    && (event.event_x as u32) < width
+                    && (event.event_y as u32) < height;
+                if !inside {
+                    self.close(connection)?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// copies the rendered calendar into the window
+    fn paint(&self, connection: &XCBConnection, window: Window) -> Result<(), crate::Error> {
+        if self.pixels.is_empty() {
+            return Ok(());
+        }
+
+        let (width, height) = Self::size();
+        let depth = connection.get_geometry(window)?.reply()?.depth;
+
+        let gc = connection.generate_id()?;
+        connection.create_gc(gc, window, &CreateGCAux::new())?;
+        // the pixels are BGRA, which is how 24 and 32 bit visuals store them on little endian
+        connection.put_image(
+            ImageFormat::Z_PIXMAP,
+            window,
+            gc,
+            width as u16,
+            height as u16,
+            0,
+            0,
+            0,
+            depth,
+            &self.pixels,
+        )?;
+        connection.free_gc(gc)?;
+        connection.flush()?;
+
+        Ok(())
+    }
+
+    /// draws the month of `today` with `today` highlighted
+    fn render(&mut self, today: NaiveDate) -> Result<(), crate::Error> {
+        let (width, height) = Self::size();
+        // the same byte order as the window's pixels, see `paint`
+        let state = self.state.get_or_insert_with(|| {
+            pollster::block_on(State::new_offscreen(
+                width,
+                height,
+                mdry::wgpu::TextureFormat::Bgra8Unorm,
+            ))
+        });
+
+        let first = today.with_day(1).ok_or("invalid date")?;
+        let next_month = match first.month() {
+            12 => NaiveDate::from_ymd_opt(first.year() + 1, 1, 1),
+            month => NaiveDate::from_ymd_opt(first.year(), month + 1, 1),
+        }
+        .ok_or("invalid date")?;
+        let days = next_month.signed_duration_since(first).num_days() as u32;
+        // the column of the first day, weeks start on monday
+        let start = first.weekday().num_days_from_monday();
+
+        let metrics = glyphon::Metrics::new(FONT_SIZE, FONT_SIZE);
+        let centered = |state: &mut State, text: &str, x: f32, y: f32, width: f32, color| {
+            let (text_width, _) = state.measure_text(text, metrics);
+            let x = (x + (width - text_width) / 2.).round();
+            let y = (y + (CELL_HEIGHT as f32 - FONT_SIZE) / 2.).round();
+            state.draw_text_absolute_cached(text, x, y, color, FONT_SIZE);
+        };
+
+        state.clear_background(self.background);
+
+        let title = first.format("%B %Y").to_string();
+        centered(state, &title, 0., 0., width as f32, self.foreground);
+
+        for (column, weekday) in WEEKDAYS.iter().enumerate() {
+            let x = (column as u32 * CELL_WIDTH) as f32;
+            let color = self.foreground.with_alpha(150);
+            centered(
+                state,
+                weekday,
+                x,
+                CELL_HEIGHT as f32,
+                CELL_WIDTH as f32,
+                color,
+            );
+        }
+
+        for day in 1..=days {
+            let cell = start + day - 1;
+            let x = ((cell % 7) * CELL_WIDTH) as f32;
+            let y = ((2 + cell / 7) * CELL_HEIGHT) as f32;
+
+            let color = if day == today.day() {
+                state.draw_shape_absolute(Shape::Rect(Rect {
+                    x: x + 2.,
+                    y: y + 2.,
+                    width: CELL_WIDTH - 4,
+                    height: CELL_HEIGHT - 4,
+                    color: self.highlight,
+                }));
+                self.background
+            } else {
+                self.foreground
+            };
+
+            centered(state, &day.to_string(), x, y, CELL_WIDTH as f32, color);
+        }
+
+        state.update()?;
+        state.render()?;
+        self.pixels = state.read_pixels().unwrap_or_default();
+
+        Ok(())
+    }
+}
@@ -1,79 +1,66 @@
-use std::{sync::Arc, time::Duration};
+use std::time::Duration;
 
-use chrono::Local;
-use crossbeam::channel::{Receiver, Sender};
-use glyphon::{Attrs, Shaping};
-use mdry::{
-    color::Color,
-    renderer::{measure_text, Font, TextInner},
-};
-use smol::stream::StreamExt;
-use systemstat::{CPULoad, Platform};
+use crossbeam::channel::Sender;
+use log::{debug, warn};
+use mdry::{color::Color, renderer::TextHandle};
+use systemstat::{CPULoad, DelayedMeasurement, Platform};
 
-use super::Widget;
+use super::{Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::{Schedule, Scheduler, TimerToken};
 
 pub struct CPUUsage {
     font_size: f32,
     color: Color,
-    text: Option<Arc<TextInner>>,
-    cpu_load_sender: Sender<CPULoad>,
-    cpu_load_receiver: Receiver<CPULoad>,
+    text: Option<TextHandle>,
+    system: systemstat::System,
+    /// started on every timer tick and read on the next one
+    measurement: Option<DelayedMeasurement<CPULoad>>,
+    cpu_load: Option<CPULoad>,
+    timer: Option<TimerToken>,
+    alignment: Alignment,
 }
 
 impl CPUUsage {
     pub fn new(font_size: f32, color: Color) -> Self {
-        let (cpu_load_sender, cpu_load_receiver) = crossbeam::channel::unbounded();
         Self {
             font_size,
             color,
             text: None,
-            cpu_load_sender,
-            cpu_load_receiver,
+            system: systemstat::System::new(),
+            measurement: None,
+            cpu_load: None,
+            timer: None,
+            alignment: Alignment::Right,
         }
     }
+
+    /// `font_size` and `color`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let font_size = config::get_f32(table, "font_size", context.bar_height as f32)?;
+        let color = config::get_color(table, "color", context.foreground)?;
+
+        Ok(Self::new(font_size, color))
+    }
 }
 
 impl Widget for CPUUsage {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
     fn setup(
         &mut self,
         state: &mut mdry::State,
         _connection: &x11rb::xcb_ffi::XCBConnection,
         _screen_num: usize,
-        redraw_sender: Sender<()>,
+        _redraw_sender: Sender<()>,
+        scheduler: &Scheduler,
     ) -> Result<(), crate::Error> {
-        let width = state.width as f32;
-        let height = state.height as f32;
-        let scale = state.window.display_scale;
-        let text = Arc::new(TextInner::new(
-            state.font_system_mut(),
-            &Local::now().format("%H:%M:%S").to_string(),
-            0.,
-            0.,
-            width * scale,
-            height * scale,
-            self.font_size,
-            self.color,
-            Font::DEFAULT,
-        ));
-
-        self.text = Some(text);
-
-        {
-            let cpu_load_sender = self.cpu_load_sender.clone();
-            std::thread::spawn(move || {
-                smol::block_on(async {
-                    let system = systemstat::System::new();
-                    loop {
-                        let measurement =
-                            system.cpu_load_aggregate().expect("could not get cpu info");
-                        smol::Timer::interval(Duration::from_secs(1)).next().await;
-                        let _ = cpu_load_sender
-                            .send(measurement.done().expect("could not read cpu load"));
-                        redraw_sender.send(()).unwrap();
-                    }
-                });
-            });
-        }
+        self.text = Some(state.create_text(" 0%", self.font_size, self.color));
+
+        self.measurement = Some(self.system.cpu_load_aggregate()?);
+        self.timer = Some(scheduler.register(Schedule::Interval(Duration::from_secs(1))));
 
         Ok(())
     }
@@ -89,79 +76,76 @@ impl Widget for CPUUsage {
         Ok(())
     }
 
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::NONE
+    }
+
     fn draw(
         &mut self,
         _connection: &x11rb::xcb_ffi::XCBConnection,
         _screen_num: usize,
         state: &mut mdry::State,
-        offset: f32,
+        _offset: f32,
     ) -> Result<(), crate::Error> {
-        let text = self.text.take().expect("text should always be initialized");
-        match Arc::try_unwrap(text) {
-            Ok(mut inner) => {
-                if let Ok(cpu_load) = self.cpu_load_receiver.try_recv() {
-                    inner.x = offset;
-                    inner.content = format!(" {}%", (cpu_load.user * 100.) as u32);
-                    inner.buffer.set_text(
-                        state.font_system_mut(),
-                        &inner.content,
-                        Attrs::new().family(inner.font.family.into_glyphon_family()),
-                        Shaping::Advanced,
-                    );
-
-                    let (width, _height) = measure_text(&inner.buffer);
-                    inner.bounds.left = inner.x as i32;
-                    inner.bounds.right = (inner.x + width) as i32;
-                }
-
-                self.text = Some(Arc::new(inner));
-            }
-            Err(_inner_arc) => {
-                let width = state.width as f32;
-                let height = state.height as f32;
-                let scale = state.window.display_scale;
-                self.text = Some(Arc::new(TextInner::new(
-                    state.font_system_mut(),
-                    &String::from(" 0%"),
-                    0.,
-                    0.,
-                    width * scale,
-                    height * scale,
-                    self.font_size,
-                    self.color,
-                    Font::DEFAULT,
-                )));
-            }
+        let text = self.text.expect("text should always be initialized");
+        if let Some(cpu_load) = self.cpu_load.take() {
+            // everything but idle, `user` alone leaves out the kernel, interrupts and so on
+            let usage = ((1. - cpu_load.idle) * 100.).round().clamp(0., 100.);
+            state.update_text(text, &format!(" {usage}%"));
         }
+        state.draw_text(text, 0., 0.);
+
+        Ok(())
+    }
 
-        if let Some(text) = &self.text {
-            state.draw_text_absolute(text.clone());
+    fn destroy(
+        &mut self,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        state: &mut mdry::State,
+    ) -> Result<(), crate::Error> {
+        if let Some(text) = self.text.take() {
+            state.remove_text(text);
         }
 
         Ok(())
     }
 
-    fn size(&mut self, _state: &mut mdry::State) -> f32 {
-        let text = self.text.take().expect("text should always be initialized");
-        let size = match Arc::try_unwrap(text) {
-            Ok(inner) => {
-                let (width, _height) = measure_text(&inner.buffer);
-                println!("width: {width}");
-                self.text = Some(Arc::new(inner));
+    fn on_timer(&mut self, token: TimerToken, _state: &mut mdry::State) -> bool {
+        if self.timer != Some(token) {
+            return false;
+        }
 
-                width
+        if let Some(measurement) = self.measurement.take() {
+            match measurement.done() {
+                Ok(cpu_load) => self.cpu_load = Some(cpu_load),
+                Err(e) => warn!("could not read cpu load: {e}"),
             }
-            Err(inner_arc) => {
-                // TODO: replace the whole thing
-                self.text = Some(inner_arc);
-                0.
-            }
-        };
+        }
+
+        match self.system.cpu_load_aggregate() {
+            Ok(measurement) => self.measurement = Some(measurement),
+            Err(e) => warn!("could not get cpu info: {e}"),
+        }
+
+        true
+    }
+
+    fn size(&mut self, state: &mut mdry::State) -> f32 {
+        let size = self
+            .text
+            .and_then(|text| state.text_size(text))
+            .map_or(0., |(width, _)| width);
+        debug!("width: {size}");
 
         size + 10.
     }
 
-    fn alignment(&self) -> super::Alignment {
-        super::Alignment::Right
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
     }
 }
@@ -0,0 +1,207 @@
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crossbeam::channel::{Receiver, Sender};
+use inotify::{Inotify, WatchMask};
+use log::error;
+use mdry::{color::Color, State};
+use x11rb::{protocol::Event, xcb_ffi::XCBConnection};
+
+use super::{Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
+
+/// The first line of a file, updated as soon as the file changes
+///
+/// the file's directory is watched with inotify instead of the file itself,
+/// so it can be created later, deleted, or replaced by renaming another file over it,
+/// nothing is shown while the file doesn't exist
+pub struct FileWatch {
+    path: PathBuf,
+    color: Color,
+    font_size: f32,
+    /// the trimmed first line of the file
+    line: String,
+    /// new lines from the watcher thread
+    lines: Option<Receiver<String>>,
+    alignment: Alignment,
+}
+
+impl FileWatch {
+    pub fn new(path: impl Into<PathBuf>, color: Color, font_size: f32) -> Self {
+        let path = path.into();
+
+        Self {
+            line: first_line(&path),
+            path,
+            color,
+            font_size,
+            lines: None,
+            alignment: Alignment::Right,
+        }
+    }
+
+    /// `path`, `color` and `font_size`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let path = config::get_str(table, "path", "")?;
+        if path.is_empty() {
+            return Err(config::invalid("path", "expected the path of a file").into());
+        }
+
+        Ok(Self::new(
+            path,
+            config::get_color(table, "color", context.foreground)?,
+            config::get_f32(table, "font_size", context.bar_height as f32)?,
+        ))
+    }
+
+    /// takes the newest line from the watcher thread
+    fn update(&mut self) {
+        if let Some(line) = self
+            .lines
+            .as_ref()
+            .and_then(|lines| lines.try_iter().last())
+        {
+            self.line = line;
+        }
+    }
+}
+
+/// the trimmed first line of the file, empty if it can't be read
+fn first_line(path: &Path) -> String {
+    std::fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+/// reads the file again whenever something happens to it in its directory,
+/// until the widget or the bar is gone
+fn watch(
+    mut inotify: Inotify,
+    path: &Path,
+    file_name: &OsString,
+    line_sender: Sender<String>,
+    redraw_sender: Sender<()>,
+) -> io::Result<()> {
+    let mut buffer = [0; 4096];
+    loop {
+        let mut events = inotify.read_events_blocking(&mut buffer)?;
+        if !events.any(|event| event.name == Some(file_name.as_os_str())) {
+            continue;
+        }
+
+        if line_sender.send(first_line(path)).is_err() || redraw_sender.send(()).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+impl Widget for FileWatch {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn setup(
+        &mut self,
+        _state: &mut State,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
+    ) -> Result<(), crate::Error> {
+        let file_name = self
+            .path
+            .file_name()
+            .ok_or_else(|| format!("{} is not a file", self.path.display()))?
+            .to_os_string();
+        let directory = match self.path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        };
+
+        // set up here, so a missing directory is reported like any other setup error
+        let inotify = Inotify::init()?;
+        inotify
+            .watches()
+            .add(
+                directory,
+                WatchMask::CREATE
+                    | WatchMask::MODIFY
+                    | WatchMask::CLOSE_WRITE
+                    | WatchMask::MOVED_TO
+                    | WatchMask::MOVED_FROM
+                    | WatchMask::DELETE,
+            )
+            .map_err(|e| format!("could not watch {}: {e}", directory.display()))?;
+
+        let (line_sender, line_receiver) = crossbeam::channel::unbounded();
+        self.lines = Some(line_receiver);
+
+        let path = self.path.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = watch(inotify, &path, &file_name, line_sender, redraw_sender) {
+                error!("stopped watching {}: {e}", path.display());
+            }
+        });
+
+        Ok(())
+    }
+
+    fn on_event(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        _state: &mut State,
+        _event: Event,
+        _redraw_sender: Sender<()>,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::NONE
+    }
+
+    fn draw(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        state: &mut State,
+        _offset: f32,
+    ) -> Result<(), crate::Error> {
+        self.update();
+        if !self.line.is_empty() {
+            state.draw_text_absolute_cached(&self.line, 0., 0., self.color, self.font_size);
+        }
+
+        Ok(())
+    }
+
+    fn size(&mut self, state: &mut State) -> f32 {
+        self.update();
+        if self.line.is_empty() {
+            return 0.;
+        }
+
+        let metrics = glyphon::Metrics::new(self.font_size, self.font_size);
+        state.measure_text(&self.line, metrics).0
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+}
@@ -1,26 +1,108 @@
+use std::{ops::BitOr, time::Instant};
+
 use crossbeam::channel::Sender;
-use x11rb::xcb_ffi::XCBConnection;
+use x11rb::{
+    protocol::{
+        xproto::{
+            ChangeWindowAttributesAux, ConnectionExt as _, EventMask, KeyButMask, Keysym, Window,
+        },
+        Event,
+    },
+    xcb_ffi::XCBConnection,
+};
+
+use mdry::{window::CursorKind, State};
 
-use mdry::State;
+use crate::scheduler::{Scheduler, TimerToken};
 
+pub mod brightness;
+pub mod calendar;
 pub mod cpu_usage;
+pub mod file_watch;
 pub mod pager;
+pub mod separator;
+pub mod spacer;
 pub mod sys_time;
 pub mod sys_tray;
+pub mod taskbar;
 pub mod text;
+pub mod window_title;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Alignment {
     Left,
     Right,
+    /// centered in the bar, clipped or dropped if it overlaps the left or right widgets
+    Center,
+}
+
+/// The kinds of X events a widget wants in [`Widget::on_event`], see [`Widget::event_mask`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventInterest(u32);
+
+impl EventInterest {
+    pub const NONE: Self = Self(0);
+    /// button presses and releases
+    pub const BUTTON: Self = Self(1 << 0);
+    /// the pointer moving over, into or out of a window
+    pub const POINTER_MOTION: Self = Self(1 << 1);
+    pub const PROPERTY_CHANGE: Self = Self(1 << 2);
+    pub const CLIENT_MESSAGE: Self = Self(1 << 3);
+    pub const EXPOSE: Self = Self(1 << 4);
+    /// windows being mapped, unmapped, destroyed, reparented or configured
+    pub const STRUCTURE: Self = Self(1 << 5);
+    /// changes in selection ownership and conversions
+    pub const SELECTION: Self = Self(1 << 6);
+    /// everything else, like key presses or extension events
+    pub const OTHER: Self = Self(1 << 7);
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// the kind of `event`
+    pub fn of(event: &Event) -> Self {
+        match event {
+            Event::ButtonPress(_) | Event::ButtonRelease(_) => Self::BUTTON,
+            Event::MotionNotify(_) | Event::EnterNotify(_) | Event::LeaveNotify(_) => {
+                Self::POINTER_MOTION
+            }
+            Event::PropertyNotify(_) => Self::PROPERTY_CHANGE,
+            Event::ClientMessage(_) => Self::CLIENT_MESSAGE,
+            Event::Expose(_) => Self::EXPOSE,
+            Event::MapNotify(_)
+            | Event::UnmapNotify(_)
+            | Event::DestroyNotify(_)
+            | Event::ReparentNotify(_)
+            | Event::ConfigureNotify(_) => Self::STRUCTURE,
+            Event::SelectionClear(_) | Event::SelectionRequest(_) | Event::SelectionNotify(_) => {
+                Self::SELECTION
+            }
+            _ => Self::OTHER,
+        }
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for EventInterest {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
 }
 
 pub trait Widget {
+    /// a short name to tell widgets apart in logs, like "pager" or "systray"
+    fn name(&self) -> &str;
+
     fn setup(
         &mut self,
         state: &mut State,
         connection: &XCBConnection,
         screen_num: usize,
         redraw_sender: Sender<()>,
+        scheduler: &Scheduler,
     ) -> Result<(), crate::Error>;
     fn on_event(
         &mut self,
@@ -31,6 +113,25 @@ pub trait Widget {
         redraw_sender: Sender<()>,
     ) -> Result<(), crate::Error>;
 
+    /// the events [`Widget::on_event`] is called with, the bar skips the widget
+    /// for everything else
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::ALL
+    }
+
+    /// called for every key press on the bar,
+    /// `keysym` is already translated with the current keyboard mapping
+    fn on_key(
+        &mut self,
+        _keysym: Keysym,
+        _modifiers: KeyButMask,
+        _state: &mut State,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    /// draws the widget from its left edge at `x = 0`, the bar translates everything drawn
+    /// to where the widget is, `offset` is only needed to match pointer events to it
     fn draw(
         &mut self,
         connection: &XCBConnection,
@@ -39,15 +140,211 @@ pub trait Widget {
         offset: f32,
     ) -> Result<(), crate::Error>;
 
+    /// called when the pointer moves onto the space the widget was last drawn in,
+    /// a good place to change the cursor or start a hover style
+    fn on_pointer_enter(
+        &mut self,
+        _connection: &XCBConnection,
+        _state: &mut State,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    /// called when the pointer moves off the widget, or leaves the bar,
+    /// after a matching [`Widget::on_pointer_enter`]
+    fn on_pointer_leave(
+        &mut self,
+        _connection: &XCBConnection,
+        _state: &mut State,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    /// called when the bar changed size, after the state was resized,
+    /// for widgets that placed something with the old size, like windows of their own
+    fn on_resize(
+        &mut self,
+        _connection: &XCBConnection,
+        _width: u32,
+        _height: u32,
+        _state: &mut State,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    /// called once before the bar exits, to give back anything the widget
+    /// holds outside the process, like X selections or reparented windows
+    fn destroy(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        _state: &mut State,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    /// called at a fixed rate for time based animations,
+    /// returns whether the widget needs to be redrawn
+    ///
+    /// the bar stops ticking once no widget needs a redraw, and ticks again after
+    /// the next event, timer or redraw, which is what can start an animation
+    fn tick(&mut self, _now: Instant, _state: &mut State) -> bool {
+        false
+    }
+
+    /// called when a timer registered with [`Scheduler::register`] fires,
+    /// every widget sees every token so ignore the ones you didn't register,
+    /// returns whether the widget needs to be redrawn
+    fn on_timer(&mut self, _token: TimerToken, _state: &mut State) -> bool {
+        false
+    }
+
     fn size(&mut self, _state: &mut State) -> f32 {
         0.
     }
 
+    /// the minimum and maximum size of the widget, `None` if it can grow indefinitely
+    ///
+    /// `preferred` is what [`Widget::size`] just returned, widgets get more or less
+    /// than that when the bar has space left or not enough space for everything
+    fn size_hint(&mut self, _state: &mut State, preferred: f32) -> (f32, Option<f32>) {
+        (preferred, Some(preferred))
+    }
+
     fn alignment(&self) -> Alignment {
         Alignment::Left
     }
 
+    /// moves the widget to another part of the bar, takes effect on the next redraw,
+    /// widgets that always stay where they are ignore it
+    fn set_alignment(&mut self, _alignment: Alignment) {}
+
+    /// whether the widget changed since it was last drawn
+    ///
+    /// widgets set their flag in [`Widget::on_event`] or [`Widget::tick`], and the bar
+    /// resets it with [`Widget::clear_redraw`] once the widget was drawn successfully,
+    /// widgets that don't track it are always considered changed
     fn requires_redraw(&self) -> bool {
         true
     }
+
+    /// called by the bar after every successful [`Widget::draw`]
+    fn clear_redraw(&mut self) {}
+}
+
+/// Which item of a widget with several items, like the desktops of the pager,
+/// the pointer is over
+///
+/// items are `(index, x, width)` in the same coordinates as the pointer events,
+/// `padding` widens every item on both sides so the gaps between items still hit one
+#[derive(Debug, Default)]
+pub struct HoverTracker {
+    padding: f32,
+    hovered: Option<usize>,
+}
+
+impl HoverTracker {
+    pub fn new(padding: f32) -> Self {
+        Self {
+            padding,
+            hovered: None,
+        }
+    }
+
+    /// the item under `pointer_x`, the first one wins where padded items overlap
+    pub fn hit_test(
+        &self,
+        items: impl IntoIterator<Item = (usize, f32, f32)>,
+        pointer_x: f32,
+    ) -> Option<usize> {
+        items
+            .into_iter()
+            .find(|&(_, x, width)| {
+                pointer_x >= x - self.padding && pointer_x <= x + width + self.padding
+            })
+            .map(|(index, _, _)| index)
+    }
+
+    /// moves the hover to the item under `pointer_x`, for `MotionNotify`,
+    /// returns whether the hovered item changed
+    pub fn update(
+        &mut self,
+        items: impl IntoIterator<Item = (usize, f32, f32)>,
+        pointer_x: f32,
+    ) -> bool {
+        let hovered = self.hit_test(items, pointer_x);
+        std::mem::replace(&mut self.hovered, hovered) != hovered
+    }
+
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    /// shows that the items are clickable, for [`Widget::on_pointer_enter`]
+    pub fn enter(&mut self, state: &State) -> Result<(), crate::Error> {
+        state.window().set_cursor(CursorKind::Pointer)?;
+
+        Ok(())
+    }
+
+    /// forgets the hovered item and restores the cursor, for [`Widget::on_pointer_leave`]
+    pub fn leave(&mut self, state: &State) -> Result<(), crate::Error> {
+        self.hovered = None;
+        state.window().set_cursor(CursorKind::Default)?;
+
+        Ok(())
+    }
+}
+
+/// adds `mask` to the events the bar selected on `window`
+///
+/// a client has a single event mask per window, so setting it outright would drop what
+/// the rest of the bar selected, like its own window's events when it's the one listened to
+pub fn select_events(
+    connection: &XCBConnection,
+    window: Window,
+    mask: EventMask,
+) -> Result<(), crate::Error> {
+    let current = connection
+        .get_window_attributes(window)?
+        .reply()?
+        .your_event_mask;
+    if current.contains(mask) {
+        return Ok(());
+    }
+
+    let change = ChangeWindowAttributesAux::new().event_mask(current | mask);
+    connection.change_window_attributes(window, &change)?;
+
+    Ok(())
+}
+
+/// shortens `text` with an ellipsis until it's at most `max_width` wide
+pub fn truncate_to_width(
+    state: &mut State,
+    text: &str,
+    metrics: glyphon::Metrics,
+    max_width: f32,
+) -> String {
+    if state.measure_text(text, metrics).0 <= max_width {
+        return text.to_string();
+    }
+
+    // the end of the first `n` characters
+    let prefix_end = |n: usize| text.char_indices().nth(n).map_or(text.len(), |(i, _)| i);
+    let chars = text.chars().count();
+
+    // the most characters that still fit with the ellipsis, found with a binary search
+    let (mut low, mut high) = (0, chars.saturating_sub(1));
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        let candidate = format!("{}…", &text[..prefix_end(mid)]);
+        if state.measure_text(&candidate, metrics).0 <= max_width {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    format!("{}…", text[..prefix_end(low)].trim_end())
 }
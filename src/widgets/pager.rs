@@ -1,11 +1,9 @@
 use crossbeam::channel::Sender;
+use log::warn;
 use x11rb::{
     connection::Connection,
     protocol::{
-        xproto::{
-            AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt, Cursor,
-            EventMask,
-        },
+        xproto::{AtomEnum, ClientMessageEvent, ConnectionExt, EventMask},
         Event,
     },
     xcb_ffi::XCBConnection,
@@ -13,12 +11,11 @@ use x11rb::{
 };
 
 use crate::State;
-use mdry::{color::Color, shapes::Rect};
-
-use super::{text::TextWidget, Widget};
+use mdry::{color::Color, shapes::Rect, window::WmInfo};
 
-const HAND_CURSOR: u16 = 60;
-const LEFTPTR_CURSOR: u16 = 68;
+use super::{text::TextWidget, Alignment, EventInterest, HoverTracker, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
 
 const LEFT_BTN: u8 = 1;
 // const RIGHT_BTN: u8 = 2;
@@ -26,7 +23,20 @@ const LEFT_BTN: u8 = 1;
 // const SCROLL_UP: u8 = 4;
 // const SCROLL_DOWN: u8 = 5;
 
+/// What the pager shows for each desktop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PagerLabelMode {
+    /// the names from `_NET_DESKTOP_NAMES`
+    Names,
+    /// `1` to the number of desktops
+    Numbers,
+    /// a label per desktop index, like icon font glyphs,
+    /// desktops past the end of the list fall back to their names, then numbers
+    Custom(Vec<String>),
+}
+
 pub struct Pager {
+    label_mode: PagerLabelMode,
     text_metrics: glyphon::Metrics,
     text_color: Color,
     current_desktop: Option<usize>,
@@ -35,10 +45,11 @@ pub struct Pager {
     requires_redraw: bool,
     padding: f32,
     width: f32,
+    /// where the pager was last drawn, pointer events are relative to the bar
+    offset: f32,
     selector_color: Color,
-    normal_cursor: Cursor,
-    hand_cursor: Cursor,
-    hovering: Option<usize>,
+    hover: HoverTracker,
+    alignment: Alignment,
 }
 
 impl Pager {
@@ -48,41 +59,10 @@ impl Pager {
         text_color: Color,
         selector_color: Color,
         padding: f32,
+        label_mode: PagerLabelMode,
     ) -> Result<Self, crate::Error> {
-        let font = connection.generate_id()?;
-        connection.open_font(font, b"cursor")?;
-
-        let hand_cursor = connection.generate_id()?;
-        connection.create_glyph_cursor(
-            hand_cursor,
-            font,
-            font,
-            HAND_CURSOR,
-            HAND_CURSOR + 1,
-            0,
-            0,
-            0,
-            u16::MAX,
-            u16::MAX,
-            u16::MAX,
-        )?;
-
-        let normal_cursor = connection.generate_id()?;
-        connection.create_glyph_cursor(
-            normal_cursor,
-            font,
-            font,
-            LEFTPTR_CURSOR,
-            LEFTPTR_CURSOR + 1,
-            0,
-            0,
-            0,
-            u16::MAX,
-            u16::MAX,
-            u16::MAX,
-        )?;
-
         Ok(Self {
+            label_mode,
             text_metrics,
             text_color,
             atoms: PagerAtoms::new(connection)?.reply()?,
@@ -90,25 +70,106 @@ impl Pager {
             desktops: Vec::new(),
             padding,
             width: 0.,
+            offset: 0.,
             current_desktop: None,
             selector_color,
-            hand_cursor,
-            normal_cursor,
-            hovering: None,
+            hover: HoverTracker::new(padding),
+            alignment: Alignment::Left,
         })
     }
+
+    /// `font_size`, `color`, `selector_color`, `padding` and `labels`,
+    /// `labels` is "names", "numbers" or a list of labels
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let font_size = config::get_f32(table, "font_size", context.bar_height as f32)?;
+
+        Self::new(
+            context.connection,
+            glyphon::Metrics::new(font_size, font_size),
+            config::get_color(table, "color", context.foreground)?,
+            config::get_color(table, "selector_color", Color::rgb(233, 86, 120))?,
+            config::get_f32(table, "padding", 5.)?,
+            get_label_mode(table)?,
+        )
+    }
+
+    /// lays the labels out one after the other, with `padding` around each one
+    fn set_desktops(&mut self, state: &mut State, labels: &[String]) {
+        let mut offset = 0.;
+        self.desktops = labels
+            .iter()
+            .map(|label| {
+                let (width, height) = state.measure_text(label, self.text_metrics);
+                let desktop = TextWidget::new(
+                    offset + self.padding,
+                    0.,
+                    label,
+                    self.text_color,
+                    self.text_metrics.font_size,
+                    None,
+                    width,
+                    height,
+                );
+                offset += width + self.padding;
+                desktop
+            })
+            .collect();
+
+        self.width = offset;
+    }
+
+    /// draws the desktops, and the selector under the current one
+    fn paint(&mut self, state: &mut State) {
+        for desktop in self.desktops.iter() {
+            desktop.paint(state);
+        }
+
+        if let Some(current_desktop_index) = self.current_desktop {
+            let current_desktop = &mut self.desktops[current_desktop_index];
+
+            let rect = Rect {
+                x: current_desktop.x(),
+                y: state.height as f32 - 2.,
+                width: current_desktop.size(state) as u32,
+                height: 2,
+                color: self.selector_color,
+            };
+
+            // draw the selector over anything else drawn in the pager
+            state.draw_shape_absolute_with_z(mdry::shapes::Shape::Rect(rect), 1.);
+        }
+    }
 }
 
 impl Widget for Pager {
+    fn name(&self) -> &str {
+        "pager"
+    }
+
     fn setup(
         &mut self,
         state: &mut State,
         connection: &XCBConnection,
         screen_num: usize,
-        redraw_sender: Sender<()>,
+        _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
     ) -> Result<(), crate::Error> {
         let screen = &connection.setup().roots[screen_num];
 
+        // an empty pager is confusing, so say why it's empty
+        let wm = WmInfo::query(connection, screen_num, &state.window().atoms)?;
+        for (atom, name) in [
+            (self.atoms._NET_DESKTOP_NAMES, "_NET_DESKTOP_NAMES"),
+            (self.atoms._NET_CURRENT_DESKTOP, "_NET_CURRENT_DESKTOP"),
+        ] {
+            if !wm.supports(atom) {
+                warn!(
+                    "{} doesn't support {name}, the pager won't show desktops",
+                    wm.name.as_deref().unwrap_or("the window manager")
+                );
+            }
+        }
+
         let desktops = connection
             .get_property(
                 false,
@@ -119,38 +180,39 @@ impl Widget for Pager {
                 u32::MAX,
             )?
             .reply()?;
-        let desktops = get_desktop_names(desktops.value);
-
-        let (offset, text_widgets) =
-            desktops
-                .iter()
-                .fold((0., Vec::new()), |(offset, mut text_widgets), t| {
-                    let (width, height) = state.measure_text(t, self.text_metrics);
-                    let mut text_widget = TextWidget::new(
-                        offset + self.padding,
-                        0.,
-                        t,
-                        self.text_color,
-                        self.text_metrics.font_size,
-                        None,
-                        width,
-                        height,
-                    );
-
-                    text_widget
-                        .setup(state, connection, screen_num, redraw_sender.clone())
-                        .unwrap();
-
-                    let offset = offset + text_widget.size(state) + self.padding;
-
-                    text_widgets.push(text_widget);
-
-                    (offset, text_widgets)
-                });
-
-        self.width = offset;
+        let names = get_desktop_names(desktops.value);
 
-        self.desktops = text_widgets;
+        // the names are null terminated, so count the desktops separately
+        // when the labels don't come from them
+        let count = connection
+            .get_property(
+                false,
+                screen.root,
+                self.atoms._NET_NUMBER_OF_DESKTOPS,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )?
+            .reply()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .map_or(names.len(), |count| count as usize);
+
+        let desktops = match &self.label_mode {
+            PagerLabelMode::Names => names,
+            PagerLabelMode::Numbers => (1..=count).map(|i| i.to_string()).collect(),
+            PagerLabelMode::Custom(labels) => (0..count)
+                .map(|i| {
+                    labels
+                        .get(i)
+                        .or(names.get(i))
+                        .cloned()
+                        .unwrap_or_else(|| (i + 1).to_string())
+                })
+                .collect(),
+        };
+
+        self.set_desktops(state, &desktops);
         let reply = connection
             .get_property(
                 false,
@@ -202,7 +264,7 @@ impl Widget for Pager {
                         let current_desktop_index = value.next().unwrap() as usize;
 
                         if current_desktop_index > self.desktops.len() - 1 {
-                            eprintln!(
+                            warn!(
                                 "tried to switch to an out of bound desktop in pager: {current_desktop_index}"
                             );
                             return Ok(());
@@ -213,38 +275,23 @@ impl Widget for Pager {
 
                 self.requires_redraw = true;
             }
-            Event::MotionNotify(event) => {
-                let event_x = event.event_x as f32;
-                let hover = self
+            // while the clock's calendar is open it grabs the pointer, and its events
+            // are in its own coordinates, so only the bar's own ones are used
+            Event::MotionNotify(event) if event.event == state.window().xid => {
+                let items = self
                     .desktops
                     .iter_mut()
                     .enumerate()
-                    .map(|(i, tw)| (i, tw.x(), tw.size(state)))
-                    .find(|(_, x, width)| hover(event_x, *x, *width, self.padding));
-
-                if let Some((i, _, _)) = hover {
-                    self.hovering = Some(i);
-                    let change = ChangeWindowAttributesAux::new().cursor(self.hand_cursor);
-
-                    connection
-                        .change_window_attributes(state.window.xid, &change)?
-                        .check()?;
-                } else {
-                    self.hovering = None;
-                    let change = ChangeWindowAttributesAux::new().cursor(self.normal_cursor);
-
-                    connection
-                        .change_window_attributes(state.window.xid, &change)?
-                        .check()?;
-                }
+                    .map(|(i, tw)| (i, self.offset + tw.x(), tw.size(state)));
+                self.hover.update(items, event.event_x as f32);
             }
-            Event::ButtonPress(event) => {
+            Event::ButtonPress(event) if event.event == state.window().xid => {
                 if event.detail == LEFT_BTN {
-                    if let Some(hovering) = self.hovering {
+                    if let Some(hovering) = self.hover.hovered() {
                         let message = ClientMessageEvent::new(
                             32,
                             screen.root,
-                            state.window.atoms._NET_CURRENT_DESKTOP,
+                            state.window().atoms._NET_CURRENT_DESKTOP,
                             [hovering as u32, CURRENT_TIME, 0, 0, 0],
                         );
 
@@ -259,32 +306,37 @@ impl Widget for Pager {
         Ok(())
     }
 
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::PROPERTY_CHANGE | EventInterest::POINTER_MOTION | EventInterest::BUTTON
+    }
+
     fn draw(
         &mut self,
-        connection: &XCBConnection,
-        screen_num: usize,
+        _connection: &XCBConnection,
+        _screen_num: usize,
         state: &mut State,
         offset: f32,
     ) -> Result<(), crate::Error> {
-        for desktop in self.desktops.iter_mut() {
-            desktop.draw(connection, screen_num, state, offset)?;
-        }
-
-        if let Some(current_desktop_index) = self.current_desktop {
-            let current_desktop = &mut self.desktops[current_desktop_index];
+        self.offset = offset;
+        self.paint(state);
 
-            let rect = Rect {
-                x: current_desktop.x() + offset,
-                y: state.height as f32 - 2.,
-                width: current_desktop.size(state) as u32,
-                height: 2,
-                color: self.selector_color,
-            };
+        Ok(())
+    }
 
-            state.draw_shape_absolute(mdry::shapes::Shape::Rect(rect));
-        }
+    fn on_pointer_enter(
+        &mut self,
+        _connection: &XCBConnection,
+        state: &mut State,
+    ) -> Result<(), crate::Error> {
+        self.hover.enter(state)
+    }
 
-        Ok(())
+    fn on_pointer_leave(
+        &mut self,
+        _connection: &XCBConnection,
+        state: &mut State,
+    ) -> Result<(), crate::Error> {
+        self.hover.leave(state)
     }
 
     fn size(&mut self, state: &mut State) -> f32 {
@@ -295,9 +347,53 @@ impl Widget for Pager {
             + self.padding
     }
 
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
     fn requires_redraw(&self) -> bool {
         self.requires_redraw
     }
+
+    fn clear_redraw(&mut self) {
+        self.requires_redraw = false;
+
+        // the desktops are drawn by the pager, so the bar never clears them
+        for desktop in self.desktops.iter_mut() {
+            desktop.clear_redraw();
+        }
+    }
+}
+
+/// the `labels` key, see [`PagerLabelMode`]
+fn get_label_mode(table: &toml::Table) -> Result<PagerLabelMode, config::ConfigError> {
+    match table.get("labels") {
+        None => Ok(PagerLabelMode::Names),
+        Some(toml::Value::String(mode)) => match mode.as_str() {
+            "names" => Ok(PagerLabelMode::Names),
+            "numbers" => Ok(PagerLabelMode::Numbers),
+            other => Err(config::invalid(
+                "labels",
+                format!("`{other}` is not one of names or numbers"),
+            )),
+        },
+        Some(toml::Value::Array(labels)) => labels
+            .iter()
+            .map(|label| match label {
+                toml::Value::String(label) => Ok(label.clone()),
+                _ => Err(config::invalid("labels", "expected a list of strings")),
+            })
+            .collect::<Result<_, _>>()
+            .map(PagerLabelMode::Custom),
+        Some(_) => Err(config::invalid(
+            "labels",
+            "expected \"names\", \"numbers\" or a list of strings",
+        )),
+    }
 }
 
 pub fn get_desktop_names(values: Vec<u8>) -> Vec<String> {
@@ -317,6 +413,55 @@ x11rb::atom_manager! {
     }
 }
 
-fn hover(event_x: f32, x: f32, width: f32, padding: f32) -> bool {
-    event_x >= x - padding && event_x <= x + width + padding
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pager(state: &mut State) -> Pager {
+        let mut pager = Pager {
+            label_mode: PagerLabelMode::Numbers,
+            text_metrics: glyphon::Metrics::new(14., 14.),
+            text_color: Color::rgb(205, 214, 244),
+            current_desktop: Some(1),
+            desktops: Vec::new(),
+            // only used for X events
+            atoms: PagerAtoms {
+                _NET_NUMBER_OF_DESKTOPS: 0,
+                _NET_CURRENT_DESKTOP: 0,
+                _NET_DESKTOP_NAMES: 0,
+                _NET_WM_NAME: 0,
+                WM_NAME: 0,
+            },
+            requires_redraw: true,
+            padding: 5.,
+            width: 0.,
+            offset: 0.,
+            selector_color: Color::rgb(233, 86, 120),
+            hover: HoverTracker::new(5.),
+            alignment: Alignment::Left,
+        };
+        let labels: Vec<String> = ["1", "2", "web", "٤"].map(String::from).into();
+        pager.set_desktops(state, &labels);
+        pager
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or a software renderer like lavapipe"]
+    fn pager_snapshot() {
+        let mut state = pollster::block_on(State::new_offscreen(
+            120,
+            20,
+            mdry::wgpu::TextureFormat::Rgba8Unorm,
+        ));
+        let mut pager = pager(&mut state);
+
+        state.clear_background(Color::rgb(30, 30, 46));
+        pager.paint(&mut state);
+        state.update().unwrap();
+        state.render().unwrap();
+
+        let frame = state.capture_frame().unwrap();
+        let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/pager.png");
+        mdry::snapshot::compare_with_golden(&frame, golden, 2).unwrap();
+    }
 }
@@ -0,0 +1,118 @@
+use crossbeam::channel::Sender;
+use mdry::{
+    color::Color,
+    shapes::{Rect, Shape},
+    State,
+};
+
+use super::{Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
+
+/// A vertical line to divide groups of widgets
+pub struct Separator {
+    color: Color,
+    /// the thickness of the line
+    width: f32,
+    /// the space on either side of the line
+    padding: f32,
+    /// how much of the bar height the line covers, from 0 to 1
+    height: f32,
+    alignment: Alignment,
+}
+
+impl Separator {
+    pub fn new(color: Color, width: f32, padding: f32, height: f32) -> Self {
+        Self {
+            color,
+            width,
+            padding,
+            height: height.clamp(0., 1.),
+            alignment: Alignment::Left,
+        }
+    }
+
+    /// `color`, `width`, `padding` and `height`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        Ok(Self::new(
+            // shapes are drawn opaque, so a dimmer line is blended with the background here
+            config::get_color(
+                table,
+                "color",
+                context.foreground.mix(&context.background, 0.6),
+            )?,
+            config::get_f32(table, "width", 1.)?,
+            config::get_f32(table, "padding", 5.)?,
+            config::get_f32(table, "height", 0.6)?,
+        ))
+    }
+}
+
+impl Widget for Separator {
+    fn name(&self) -> &str {
+        "separator"
+    }
+
+    fn setup(
+        &mut self,
+        _state: &mut State,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn on_event(
+        &mut self,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        _state: &mut State,
+        _event: x11rb::protocol::Event,
+        _redraw_sender: Sender<()>,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::NONE
+    }
+
+    fn draw(
+        &mut self,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        state: &mut State,
+        _offset: f32,
+    ) -> Result<(), crate::Error> {
+        let bar_height = state.height as f32;
+        let height = (bar_height * self.height).round();
+
+        state.draw_shape_absolute(Shape::Rect(Rect {
+            x: self.padding,
+            y: ((bar_height - height) / 2.).round(),
+            width: self.width as u32,
+            height: height as u32,
+            color: self.color,
+        }));
+
+        Ok(())
+    }
+
+    fn size(&mut self, _state: &mut State) -> f32 {
+        self.width + 2. * self.padding
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    fn requires_redraw(&self) -> bool {
+        false
+    }
+}
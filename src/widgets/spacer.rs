@@ -0,0 +1,114 @@
+use crossbeam::channel::Sender;
+use mdry::State;
+
+use super::{Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
+
+/// An empty gap between widgets
+///
+/// a fixed spacer always takes the same width, a flexible one takes
+/// whatever space is left after the other widgets are laid out
+pub struct Spacer {
+    width: f32,
+    flex: bool,
+    alignment: Alignment,
+}
+
+impl Spacer {
+    pub fn fixed(width: f32) -> Self {
+        Self {
+            width,
+            flex: false,
+            alignment: Alignment::Left,
+        }
+    }
+
+    pub fn flex() -> Self {
+        Self {
+            width: 0.,
+            flex: true,
+            alignment: Alignment::Left,
+        }
+    }
+
+    /// either `width` for a fixed spacer, or `flex = true`
+    pub fn from_config(
+        table: &toml::Table,
+        _context: &WidgetContext,
+    ) -> Result<Self, crate::Error> {
+        let spacer = if config::get_bool(table, "flex", false)? {
+            Self::flex()
+        } else {
+            Self::fixed(config::get_f32(table, "width", 0.)?)
+        };
+
+        Ok(spacer)
+    }
+}
+
+impl Widget for Spacer {
+    fn name(&self) -> &str {
+        "spacer"
+    }
+
+    fn setup(
+        &mut self,
+        _state: &mut State,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn on_event(
+        &mut self,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        _state: &mut State,
+        _event: x11rb::protocol::Event,
+        _redraw_sender: Sender<()>,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::NONE
+    }
+
+    fn draw(
+        &mut self,
+        _connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        _state: &mut State,
+        _offset: f32,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn size(&mut self, _state: &mut State) -> f32 {
+        self.width
+    }
+
+    fn size_hint(&mut self, _state: &mut State, _preferred: f32) -> (f32, Option<f32>) {
+        if self.flex {
+            (0., None)
+        } else {
+            (self.width, Some(self.width))
+        }
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    fn requires_redraw(&self) -> bool {
+        false
+    }
+}
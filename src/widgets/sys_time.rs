@@ -1,77 +1,167 @@
-use std::{sync::Arc, time::Duration};
+use std::time::Duration;
 
-use chrono::Local;
-use crossbeam::channel::Sender;
-use glyphon::{Attrs, Metrics, Shaping};
-use mdry::{
-    color::Color,
-    renderer::{measure_text, Font, TextInner},
+use chrono::{
+    format::{Fixed, Item, Numeric, StrftimeItems},
+    Local,
 };
-use smol::stream::StreamExt;
+use crossbeam::channel::Sender;
+use glyphon::Metrics;
+use mdry::{color::Color, renderer::TextHandle};
+
+use super::{calendar::CalendarPopup, Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::{Schedule, Scheduler, TimerToken};
+
+pub const DEFAULT_FORMAT: &str = "%H:%M:%S";
 
-use super::Widget;
+const LEFT_BUTTON: u8 = 1;
 
+/// The current time, clicking it opens a calendar of the month if one was added
+/// with [`SysTime::with_calendar`]
 pub struct SysTime {
+    format: String,
     font_size: f32,
     color: Color,
-    text: Option<Arc<TextInner>>,
+    text: Option<TextHandle>,
+    timer: Option<TimerToken>,
+    calendar: Option<CalendarPopup>,
+    /// where the widget was last drawn, to hit test clicks
+    offset: f32,
+    width: f32,
+    alignment: Alignment,
 }
 
 impl SysTime {
-    pub fn new(font_size: f32, color: Color) -> Self {
-        Self {
+    /// `format` is a [`chrono::format::strftime`] format string, like "%H:%M:%S"
+    pub fn new(font_size: f32, color: Color, format: &str) -> Result<Self, crate::Error> {
+        // chrono only reports bad specifiers when formatting, so check them once here
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("invalid clock format `{format}`").into());
+        }
+
+        Ok(Self {
+            format: format.to_string(),
             font_size,
             color,
             text: None,
+            timer: None,
+            calendar: None,
+            offset: 0.,
+            width: 0.,
+            alignment: Alignment::Right,
+        })
+    }
+
+    fn now(&self) -> String {
+        Local::now().format(&self.format).to_string()
+    }
+
+    /// `font_size`, `color`, `format`, `calendar`, `calendar_background` and `highlight`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let font_size = config::get_f32(table, "font_size", context.bar_height as f32)?;
+        let color = config::get_color(table, "color", context.foreground)?;
+        let format = config::get_str(table, "format", DEFAULT_FORMAT)?;
+
+        let mut clock = Self::new(font_size, color, format)?;
+        if config::get_bool(table, "calendar", true)? {
+            clock = clock.with_calendar(
+                config::get_color(table, "calendar_background", context.background)?,
+                config::get_color(table, "highlight", Color::rgb(233, 86, 120))?,
+            );
         }
+
+        Ok(clock)
+    }
+
+    /// opens a calendar of the current month when the clock is clicked,
+    /// drawn with the clock's color on `background`, with today in `highlight`
+    pub fn with_calendar(mut self, background: Color, highlight: Color) -> Self {
+        self.calendar = Some(CalendarPopup::new(background, self.color, highlight));
+        self
     }
 }
 
 impl Widget for SysTime {
+    fn name(&self) -> &str {
+        "clock"
+    }
+
     fn setup(
         &mut self,
         state: &mut mdry::State,
         _connection: &x11rb::xcb_ffi::XCBConnection,
         _screen_num: usize,
-        redraw_sender: Sender<()>,
+        _redraw_sender: Sender<()>,
+        scheduler: &Scheduler,
     ) -> Result<(), crate::Error> {
-        let width = state.width as f32;
-        let height = state.height as f32;
-        let scale = state.window.display_scale;
-        let text = Arc::new(TextInner::new(
-            state.font_system_mut(),
-            &Local::now().format("%H:%M:%S").to_string(),
-            0.,
-            0.,
-            width * scale,
-            height * scale,
-            self.font_size,
-            self.color,
-            Font::DEFAULT,
-        ));
-
-        self.text = Some(text);
-
-        std::thread::spawn(move || {
-            smol::block_on(async {
-                loop {
-                    smol::Timer::interval(Duration::from_secs(1)).next().await;
-                    redraw_sender.send(()).unwrap();
-                }
-            });
-        });
+        self.text = Some(state.create_text(&self.now(), self.font_size, self.color));
+
+        let schedule = if shows_seconds(&self.format) {
+            Schedule::Interval(Duration::from_secs(1))
+        } else {
+            // wake up right when the minute changes, instead of up to a minute late
+            Schedule::EveryMinute
+        };
+        self.timer = Some(scheduler.register(schedule));
 
         Ok(())
     }
 
     fn on_event(
         &mut self,
-        _connection: &x11rb::xcb_ffi::XCBConnection,
+        connection: &x11rb::xcb_ffi::XCBConnection,
         _screen_num: usize,
-        _state: &mut mdry::State,
-        _event: x11rb::protocol::Event,
+        state: &mut mdry::State,
+        event: x11rb::protocol::Event,
         _redraw_sender: Sender<()>,
     ) -> Result<(), crate::Error> {
+        let Some(calendar) = &mut self.calendar else {
+            return Ok(());
+        };
+
+        // while it's open the popup gets every click, and closes on the ones outside of it
+        if calendar.handle_event(connection, &event)? {
+            return Ok(());
+        }
+
+        let x11rb::protocol::Event::ButtonPress(event) = event else {
+            return Ok(());
+        };
+
+        let event_x = event.event_x as f32;
+        if event.event != state.window().xid
+            || event.detail != LEFT_BUTTON
+            || event_x < self.offset
+            || event_x > self.offset + self.width
+        {
+            return Ok(());
+        }
+
+        calendar.open(connection, state, self.offset + self.width / 2.)?;
+
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        match self.calendar {
+            Some(_) => EventInterest::BUTTON | EventInterest::EXPOSE,
+            None => EventInterest::NONE,
+        }
+    }
+
+    fn destroy(
+        &mut self,
+        connection: &x11rb::xcb_ffi::XCBConnection,
+        _screen_num: usize,
+        state: &mut mdry::State,
+    ) -> Result<(), crate::Error> {
+        if let Some(text) = self.text.take() {
+            state.remove_text(text);
+        }
+        if let Some(calendar) = &mut self.calendar {
+            calendar.destroy(connection)?;
+        }
+
         Ok(())
     }
 
@@ -82,73 +172,55 @@ impl Widget for SysTime {
         state: &mut mdry::State,
         offset: f32,
     ) -> Result<(), crate::Error> {
-        let text = self.text.take().expect("text should always be initialized");
-        match Arc::try_unwrap(text) {
-            Ok(mut inner) => {
-                inner.x = offset;
-                inner.content = Local::now().format("%H:%M:%S").to_string();
-                inner.buffer.set_text(
-                    state.font_system_mut(),
-                    &inner.content,
-                    Attrs::new().family(inner.font.family.into_glyphon_family()),
-                    Shaping::Advanced,
-                );
-
-                let (width, height) = measure_text(&inner.buffer);
-                inner.bounds.right = (inner.x + width) as i32;
-                inner.bounds.bottom = (inner.y + height) as i32;
-                inner
-                    .buffer
-                    .set_size(state.font_system_mut(), width, height);
-
-                self.text = Some(Arc::new(inner));
-            }
-            Err(inner_arc) => {
-                let width = state.width as f32;
-                let height = state.height as f32;
-                let scale = state.window.display_scale;
-                self.text = Some(Arc::new(TextInner::new(
-                    state.font_system_mut(),
-                    &Local::now().format("%H:%M:%S").to_string(),
-                    0.,
-                    0.,
-                    width * scale,
-                    height * scale,
-                    self.font_size,
-                    self.color,
-                    Font::DEFAULT,
-                )));
-                self.text = Some(inner_arc);
-            }
-        }
-
-        if let Some(text) = &self.text {
-            state.draw_text_absolute(text.clone());
-        }
+        self.offset = offset;
+        let text = self.text.expect("text should always be initialized");
+        // most redraws are for other widgets, and the text is the same
+        state.update_text(text, &self.now());
+        state.draw_text(text, 0., 0.);
 
         Ok(())
     }
 
-    fn size(&mut self, _state: &mut mdry::State) -> f32 {
-        let text = self.text.take().expect("text should always be initialized");
-        let size = match Arc::try_unwrap(text) {
-            Ok(inner) => {
-                let (width, _height) = measure_text(&inner.buffer);
-                self.text = Some(Arc::new(inner));
-
-                width
-            }
-            Err(inner_arc) => {
-                // TODO: replace the whole thing
-                self.text = Some(inner_arc);
-                0.
-            }
-        };
+    fn on_timer(&mut self, token: TimerToken, _state: &mut mdry::State) -> bool {
+        self.timer == Some(token)
+    }
+
+    fn size(&mut self, state: &mut mdry::State) -> f32 {
+        let size = self
+            .text
+            .and_then(|text| state.text_size(text))
+            .map_or(0., |(width, _)| width);
+
+        self.width = size + 10.;
+        self.width
+    }
 
-        size + 10.
+    fn alignment(&self) -> Alignment {
+        self.alignment
     }
 
-    fn alignment(&self) -> super::Alignment {
-        super::Alignment::Right
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
     }
 }
+
+/// whether the format changes more often than every minute,
+/// composite specifiers like `%T` are already expanded by [`StrftimeItems`]
+fn shows_seconds(format: &str) -> bool {
+    StrftimeItems::new(format).any(|item| {
+        matches!(
+            item,
+            Item::Numeric(
+                Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp,
+                _
+            ) | Item::Fixed(
+                Fixed::Nanosecond
+                    | Fixed::Nanosecond3
+                    | Fixed::Nanosecond6
+                    | Fixed::Nanosecond9
+                    | Fixed::RFC2822
+                    | Fixed::RFC3339
+            )
+        )
+    })
+}
@@ -1,17 +1,20 @@
 use crossbeam::channel::Sender;
-use mdry::{color::Color, x11rb::Event, State};
+use log::{debug, info, warn};
+use mdry::{color::Color, window::WindowType, x11rb::Event, State};
 use x11rb::{
     connection::Connection,
     protocol::xproto::{
         AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux, ConnectionExt,
-        CreateWindowAux, EventMask, PropMode, SetMode, Window, WindowClass,
+        CreateWindowAux, EventMask, NotifyDetail, PropMode, SetMode, Window, WindowClass,
     },
     wrapper::ConnectionExt as _,
     xcb_ffi::XCBConnection,
     COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME,
 };
 
-use super::Widget;
+use super::{Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
 
 // https://specifications.freedesktop.org/systemtray-spec/systemtray-spec-0.2.html#messages
 // #define SYSTEM_TRAY_REQUEST_DOCK    0
@@ -38,15 +41,11 @@ const SYSTEM_TRAY_CANCEL_MESSAGE: u32 = 2;
 // #define XEMBED_UNREGISTER_ACCELERATOR   13
 // #define XEMBED_ACTIVATE_ACCELERATOR     14
 const XEMBED_EMBEDDED_NOTIFY: u32 = 0;
-#[allow(unused)]
 const XEMBED_WINDOW_ACTIVATE: u32 = 1;
-#[allow(unused)]
 const XEMBED_WINDOW_DEACTIVATE: u32 = 2;
 #[allow(unused)]
 const XEMBED_REQUEST_FOCUS: u32 = 3;
-#[allow(unused)]
 const XEMBED_FOCUS_IN: u32 = 4;
-#[allow(unused)]
 const XEMBED_FOCUS_OUT: u32 = 5;
 #[allow(unused)]
 const XEMBED_FOCUS_NEXT: u32 = 6;
@@ -64,6 +63,10 @@ const XEMBED_UNREGISTER_ACCELERATOR: u32 = 13;
 #[allow(unused)]
 const XEMBED_ACTIVATE_ACCELERATOR: u32 = 14;
 
+// /* Details for  XEMBED_FOCUS_IN: */
+// #define XEMBED_FOCUS_CURRENT            0
+const XEMBED_FOCUS_CURRENT: u32 = 0;
+
 const XEMBED_VERSION: u32 = 0;
 // /* Flags for _XEMBED_INFO */
 // #define XEMBED_MAPPED                   (1 << 0)
@@ -73,13 +76,32 @@ const XEMBED_VERSION: u32 = 0;
 /// the client and map and unmap the client appropriately
 const XEMBED_MAPPED: u32 = 1 << 0;
 
+/// The direction tray icons are laid out in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrayOrientation {
+    #[default]
+    Horizontal,
+    /// for bars on the side of the screen, icons are stacked along y
+    Vertical,
+}
+
 pub struct SysTray {
     selection_owner: Window,
     tray_icons: Vec<TrayIcon>,
+    /// the tray selection of the screen, interned in [`Widget::setup`]
     _net_system_tray_s: u32,
     icons_size: u32,
     padding: u32,
     background_color: Color,
+    orientation: TrayOrientation,
+    /// whether we own the tray selection and manage tray icons
+    active: bool,
+    /// the tray that owns the selection while we're inactive,
+    /// the selection is reclaimed when it's destroyed
+    other_owner: Option<Window>,
+    /// the bar's x, y, width and height the selection owner was last placed for
+    owner_geometry: Option<(i32, i32, u32, u32)>,
+    alignment: Alignment,
 }
 
 #[derive(Debug)]
@@ -121,23 +143,264 @@ impl SysTray {
             )?
             .check()?;
 
-        let atom_name = format!("_NET_SYSTEM_TRAY_S{}", screen_num);
-
-        let _net_system_tray_s = connection
-            .intern_atom(false, atom_name.as_bytes())?
-            .reply()?
-            .atom;
-
         Ok(Self {
             selection_owner: win_id,
             tray_icons: Vec::new(),
-            _net_system_tray_s,
+            _net_system_tray_s: x11rb::NONE,
             icons_size,
             padding,
             background_color,
+            orientation: TrayOrientation::default(),
+            active: false,
+            other_owner: None,
+            owner_geometry: None,
+            alignment: Alignment::Right,
         })
     }
 
+    /// `icon_size`, `padding`, `background` and `orientation` ("horizontal" or "vertical")
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let orientation = match config::get_str(table, "orientation", "horizontal")? {
+            "horizontal" => TrayOrientation::Horizontal,
+            "vertical" => TrayOrientation::Vertical,
+            other => return Err(format!("unknown systray orientation `{other}`").into()),
+        };
+
+        Ok(Self::new(
+            context.connection,
+            context.screen_num,
+            context.bar_width,
+            context.bar_height,
+            config::get_u32(table, "icon_size", 20)?,
+            config::get_u32(table, "padding", 5)?,
+            config::get_color(table, "background", context.background)?,
+        )?
+        .with_orientation(orientation))
+    }
+
+    /// claims the tray selection if it's free, otherwise watches the current owner
+    /// so the selection can be claimed once it goes away
+    fn acquire_selection(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        state: &State,
+    ) -> Result<(), Error> {
+        let screen = &connection.setup().roots[screen_num];
+
+        let owner = connection
+            .get_selection_owner(self._net_system_tray_s)?
+            .reply()?
+            .owner;
+
+        if owner == x11rb::NONE {
+            connection
+                .set_selection_owner(self.selection_owner, self._net_system_tray_s, CURRENT_TIME)?
+                .check()?;
+
+            let change = ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY);
+
+            connection
+                .change_window_attributes(self.selection_owner, &change)?
+                .check()?;
+
+            // notify clients of new selection owner
+            let event = ClientMessageEvent::new(
+                32,
+                screen.root,
+                state.window().atoms.MANAGER,
+                [
+                    CURRENT_TIME,
+                    self._net_system_tray_s,
+                    self.selection_owner,
+                    0,
+                    0,
+                ],
+            );
+
+            connection
+                .send_event(false, screen.root, EventMask::from(0xFFFFFFu32), event)?
+                .check()?;
+
+            connection.flush()?;
+
+            self.active = true;
+            self.other_owner = None;
+        } else {
+            warn!("selections already owned by: {}", owner);
+            self.watch_owner(connection, owner)?;
+        }
+
+        Ok(())
+    }
+
+    /// get a DestroyNotify when the tray that took the selection exits
+    fn watch_owner(&mut self, connection: &XCBConnection, owner: Window) -> Result<(), Error> {
+        let change = ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY);
+        connection
+            .change_window_attributes(owner, &change)?
+            .check()?;
+
+        self.active = false;
+        self.other_owner = Some(owner);
+
+        Ok(())
+    }
+
+    /// sends an XEMBED message to an embedded tray icon
+    /// https://specifications.freedesktop.org/xembed-spec/xembed-spec-latest.html#idm45171900597248
+    fn send_xembed(
+        connection: &XCBConnection,
+        state: &State,
+        embedded_window: Window,
+        message: u32,
+        detail: u32,
+    ) -> Result<(), Error> {
+        let send_event = ClientMessageEvent::new(
+            32,
+            embedded_window,
+            state.window().atoms._XEMBED,
+            [CURRENT_TIME, message, detail, 0, 0],
+        );
+
+        connection
+            .send_event(false, embedded_window, EventMask::NO_EVENT, send_event)?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// tells the icon it's active and focused, some applets ignore input otherwise
+    fn focus_icon(
+        &self,
+        connection: &XCBConnection,
+        state: &State,
+        wrapper_window: Window,
+    ) -> Result<(), Error> {
+        if let Some(ti) = self
+            .tray_icons
+            .iter()
+            .find(|ti| ti.wrapper_window == wrapper_window)
+        {
+            Self::send_xembed(
+                connection,
+                state,
+                ti.embedded_window,
+                XEMBED_WINDOW_ACTIVATE,
+                0,
+            )?;
+            Self::send_xembed(
+                connection,
+                state,
+                ti.embedded_window,
+                XEMBED_FOCUS_IN,
+                XEMBED_FOCUS_CURRENT,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn unfocus_icon(
+        &self,
+        connection: &XCBConnection,
+        state: &State,
+        wrapper_window: Window,
+    ) -> Result<(), Error> {
+        if let Some(ti) = self
+            .tray_icons
+            .iter()
+            .find(|ti| ti.wrapper_window == wrapper_window)
+        {
+            Self::send_xembed(connection, state, ti.embedded_window, XEMBED_FOCUS_OUT, 0)?;
+            Self::send_xembed(
+                connection,
+                state,
+                ti.embedded_window,
+                XEMBED_WINDOW_DEACTIVATE,
+                0,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// stops managing the tray icons, after another tray took the selection or when exiting
+    fn release_icons(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+    ) -> Result<(), Error> {
+        let root = connection.setup().roots[screen_num].root;
+
+        for ti in self.tray_icons.drain(..) {
+            // move the icon out before destroying the wrapper so it isn't destroyed with it,
+            // the new tray will embed it again
+            connection.unmap_window(ti.embedded_window)?;
+            connection.reparent_window(ti.embedded_window, root, 0, 0)?;
+            connection.change_save_set(SetMode::DELETE, ti.embedded_window)?;
+            connection.destroy_window(ti.wrapper_window)?;
+        }
+
+        connection.flush()?;
+
+        Ok(())
+    }
+
+    /// sets the direction icons are laid out in, and the orientation advertised to tray clients
+    pub fn with_orientation(mut self, orientation: TrayOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// where icons start across the bar, to center them in its height,
+    /// or its width for a vertical tray
+    fn across(&self, bar_width: u32, bar_height: u32) -> i16 {
+        let bar_size = match self.orientation {
+            TrayOrientation::Horizontal => bar_height,
+            TrayOrientation::Vertical => bar_width,
+        };
+
+        (bar_size / 2).saturating_sub(self.icons_size / 2) as i16
+    }
+
+    /// moves the selection owner to the end of the bar and reserves the bar's space with it,
+    /// again whenever the bar moves or the usable area of the screen changes
+    fn sync_owner_geometry(
+        &mut self,
+        connection: &XCBConnection,
+        state: &State,
+    ) -> Result<(), Error> {
+        let window = state.window();
+        // changing the strut changes the work area again
+        let geometry = (window.x, window.y, window.width, window.height);
+        if self.owner_geometry == Some(geometry) {
+            return Ok(());
+        }
+        self.owner_geometry = Some(geometry);
+
+        let configure = ConfigureWindowAux::new()
+            .x(window.x + window.width as i32)
+            .y(window.y)
+            .height(window.height);
+        connection.configure_window(self.selection_owner, &configure)?;
+
+        // only a dock reserves space, at the same edge as the bar
+        let WindowType::Dock { bottom, .. } = window.window_type else {
+            return Ok(());
+        };
+        let screen_height = connection.setup().roots[window.screen_num].height_in_pixels;
+        let (_, struts) = crate::dock_struts(
+            screen_height,
+            window.x as i16,
+            window.width as u16,
+            window.height as u16,
+            bottom,
+        );
+
+        crate::set_struts(connection, self.selection_owner, &window.atoms, &struts)
+    }
+
     fn embed_client(
         &mut self,
         connection: &XCBConnection,
@@ -155,11 +418,13 @@ impl SysTray {
                 .find(|ti| ti.embedded_window == embedded_window)
                 .is_some()
             {
-                eprintln!("Tray client {embedded_window} is already embedded, ignoring request...");
+                debug!("Tray client {embedded_window} is already embedded, ignoring request...");
                 return Ok(());
             }
 
-            let configure = ConfigureWindowAux::new().width(20).height(20);
+            let configure = ConfigureWindowAux::new()
+                .width(self.icons_size)
+                .height(self.icons_size);
 
             connection
                 .configure_window(embedded_window, &configure)?
@@ -174,20 +439,29 @@ impl SysTray {
             // and also match the  geometry of the embedded window
             let wrapper_window = connection.generate_id()?;
 
-            let create =
-                CreateWindowAux::new().background_pixel(self.background_color.to_argb_u32());
-
-            let y = ((state.height / 2) - self.icons_size / 2) as i16;
+            // enter/leave are used to forward focus to the icon, see `send_xembed`
+            let create = CreateWindowAux::new()
+                .background_pixel(self.background_color.to_argb_u32())
+                .event_mask(
+                    EventMask::ENTER_WINDOW | EventMask::LEAVE_WINDOW | EventMask::BUTTON_PRESS,
+                );
+
+            // center the icon across the bar, draw positions it along the bar
+            let across = self.across(state.width, state.height);
+            let (x, y) = match self.orientation {
+                TrayOrientation::Horizontal => (0, across),
+                TrayOrientation::Vertical => (across, 0),
+            };
 
             connection
                 .create_window(
                     COPY_DEPTH_FROM_PARENT,
                     wrapper_window,
-                    state.window.xid,
-                    0,
+                    state.window().xid,
+                    x,
                     y,
-                    20,
-                    20,
+                    self.icons_size as u16,
+                    self.icons_size as u16,
                     0,
                     WindowClass::INPUT_OUTPUT,
                     COPY_FROM_PARENT,
@@ -216,8 +490,8 @@ impl SysTray {
                 .get_property(
                     false,
                     embedded_window,
-                    state.window.atoms._XEMBED_INFO,
-                    state.window.atoms._XEMBED_INFO,
+                    state.window().atoms._XEMBED_INFO,
+                    state.window().atoms._XEMBED_INFO,
                     0,
                     2,
                 )?
@@ -235,7 +509,7 @@ impl SysTray {
             let send_event = ClientMessageEvent::new(
                 32,
                 embedded_window,
-                state.window.atoms._XEMBED,
+                state.window().atoms._XEMBED,
                 [
                     CURRENT_TIME,           // x_time
                     XEMBED_EMBEDDED_NOTIFY, // message
@@ -257,9 +531,9 @@ impl SysTray {
 
             self.tray_icons.push(tray_icon);
         } else if message == SYSTEM_TRAY_BEGIN_MESSAGE {
-            println!("got SYSTEM_TRAY_BEGIN_MESSAGE");
+            debug!("got SYSTEM_TRAY_BEGIN_MESSAGE");
         } else if message == SYSTEM_TRAY_CANCEL_MESSAGE {
-            println!("got SYSTEM_TRAY_CANCEL_MESSAGE");
+            debug!("got SYSTEM_TRAY_CANCEL_MESSAGE");
         }
 
         Ok(())
@@ -267,31 +541,45 @@ impl SysTray {
 }
 
 impl Widget for SysTray {
+    fn name(&self) -> &str {
+        "systray"
+    }
+
     fn setup(
         &mut self,
         state: &mut mdry::State,
         connection: &XCBConnection,
         screen_num: usize,
         _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
     ) -> Result<(), crate::Error> {
-        let screen = &connection.setup().roots[screen_num];
+        self._net_system_tray_s = state
+            .window()
+            .atom_cache
+            .intern(connection, &format!("_NET_SYSTEM_TRAY_S{screen_num}"))?;
+
         connection
             .change_property32(
                 PropMode::REPLACE,
                 self.selection_owner,
-                state.window.atoms._NET_SYSTEM_TRAY_COLORS,
+                state.window().atoms._NET_SYSTEM_TRAY_COLORS,
                 AtomEnum::CARDINAL,
                 &[26, 29, 36],
             )?
             .check()?;
 
+        let orientation = match self.orientation {
+            TrayOrientation::Horizontal => state.window().atoms._NET_SYSTEM_TRAY_ORIENTATION_HORZ,
+            TrayOrientation::Vertical => state.window().atoms._NET_SYSTEM_TRAY_ORIENTATION_VERT,
+        };
+
         connection
             .change_property32(
                 PropMode::REPLACE,
                 self.selection_owner,
-                state.window.atoms._NET_SYSTEM_TRAY_ORIENTATION,
+                state.window().atoms._NET_SYSTEM_TRAY_ORIENTATION,
                 AtomEnum::CARDINAL,
-                &[state.window.atoms._NET_SYSTEM_TRAY_ORIENTATION_HORZ],
+                &[orientation],
             )?
             .check()?;
 
@@ -299,76 +587,15 @@ impl Widget for SysTray {
             .change_property32(
                 PropMode::REPLACE,
                 self.selection_owner,
-                state.window.atoms._NET_WM_WINDOW_TYPE,
+                state.window().atoms._NET_WM_WINDOW_TYPE,
                 AtomEnum::ATOM,
-                &[state.window.atoms._NET_WM_WINDOW_TYPE_DOCK],
+                &[state.window().atoms._NET_WM_WINDOW_TYPE_DOCK],
             )?
             .check()?;
 
-        connection
-            .change_property32(
-                PropMode::REPLACE,
-                self.selection_owner,
-                state.window.atoms._NET_WM_STRUT_PARTIAL,
-                AtomEnum::CARDINAL,
-                // left, right, top, bottom, left_start_y, left_end_y,
-                // right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x,
-                // bottom_end_x
-                &[
-                    0,
-                    0,
-                    state.window.height,
-                    0,
-                    0,
-                    0,
-                    0,
-                    0,
-                    state.window.x as u32,
-                    state.window.width,
-                    0,
-                    0,
-                ],
-            )?
-            .check()?;
-
-        let owner = connection
-            .get_selection_owner(self._net_system_tray_s)?
-            .reply()?
-            .owner;
-
-        if owner == x11rb::NONE {
-            connection
-                .set_selection_owner(self.selection_owner, self._net_system_tray_s, CURRENT_TIME)?
-                .check()?;
-
-            let change = ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY);
-
-            connection
-                .change_window_attributes(self.selection_owner, &change)?
-                .check()?;
-
-            // notify clients of new selection owner
-            let event = ClientMessageEvent::new(
-                32,
-                screen.root,
-                state.window.atoms.MANAGER,
-                [
-                    CURRENT_TIME,
-                    self._net_system_tray_s,
-                    self.selection_owner,
-                    0,
-                    0,
-                ],
-            );
-
-            connection
-                .send_event(false, screen.root, EventMask::from(0xFFFFFFu32), event)?
-                .check()?;
+        self.sync_owner_geometry(connection, state)?;
 
-            connection.flush()?;
-        } else {
-            eprintln!("selections already owned by: {}", owner);
-        }
+        self.acquire_selection(connection, screen_num, state)?;
 
         Ok(())
     }
@@ -376,28 +603,80 @@ impl Widget for SysTray {
     fn on_event(
         &mut self,
         connection: &XCBConnection,
-        _screen_num: usize,
+        screen_num: usize,
         state: &mut mdry::State,
         event: x11rb::protocol::Event,
         redraw_sender: Sender<()>,
     ) -> Result<(), crate::Error> {
         match event {
+            Event::SelectionClear(event)
+                if event.selection == self._net_system_tray_s
+                    && event.owner == self.selection_owner =>
+            {
+                warn!("another tray took the system tray selection");
+                self.release_icons(connection, screen_num)?;
+
+                let owner = connection
+                    .get_selection_owner(self._net_system_tray_s)?
+                    .reply()?
+                    .owner;
+
+                if owner == x11rb::NONE {
+                    self.acquire_selection(connection, screen_num, state)?;
+                } else {
+                    self.watch_owner(connection, owner)?;
+                }
+
+                redraw_sender.send(())?;
+            }
+            Event::DestroyNotify(event) if Some(event.window) == self.other_owner => {
+                self.acquire_selection(connection, screen_num, state)?;
+                if self.active {
+                    info!("reclaimed the system tray selection");
+                }
+            }
+            Event::EnterNotify(event) => {
+                self.focus_icon(connection, state, event.event)?;
+            }
+            Event::ButtonPress(event) => {
+                self.focus_icon(connection, state, event.event)?;
+            }
+            // moving into the icon itself also leaves the wrapper
+            Event::LeaveNotify(event) if event.detail != NotifyDetail::INFERIOR => {
+                self.unfocus_icon(connection, state, event.event)?;
+            }
             Event::ClientMessage(event) => {
-                if event.type_ == state.window.atoms._NET_SYSTEM_TRAY_OPCODE {
+                if event.type_ == state.window().atoms._NET_SYSTEM_TRAY_OPCODE {
+                    if !self.active {
+                        return Ok(());
+                    }
+
                     let message_data = event.data.as_data32();
                     self.embed_client(connection, message_data, &state)?;
                     return Ok(());
                 }
 
                 if event.type_ == self._net_system_tray_s {
-                    println!("systray event");
+                    debug!("systray event");
                 }
             }
             Event::Expose(event) => {
                 if event.window == self.selection_owner {
-                    println!("{event:#?}");
+                    debug!("{event:#?}");
                 }
             }
+            // another dock reserved or released space, which may have moved the bar
+            Event::PropertyNotify(event)
+                if event.window == connection.setup().roots[screen_num].root
+                    && event.atom == state.window().atoms._NET_WORKAREA =>
+            {
+                self.on_resize(connection, state.width, state.height, state)?;
+                redraw_sender.send(())?;
+            }
+            // the work area can change before the bar is moved, so the owner follows the bar
+            Event::ConfigureNotify(event) if event.window == state.window().xid => {
+                self.sync_owner_geometry(connection, state)?;
+            }
             Event::PropertyNotify(event) => {
                 if let Some(tray_icon) = self
                     .tray_icons
@@ -408,8 +687,8 @@ impl Widget for SysTray {
                         .get_property(
                             false,
                             tray_icon.embedded_window,
-                            state.window.atoms._XEMBED_INFO,
-                            state.window.atoms._XEMBED_INFO,
+                            state.window().atoms._XEMBED_INFO,
+                            state.window().atoms._XEMBED_INFO,
                             0,
                             2,
                         )?
@@ -457,6 +736,16 @@ impl Widget for SysTray {
         Ok(())
     }
 
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::SELECTION
+            | EventInterest::STRUCTURE
+            | EventInterest::POINTER_MOTION
+            | EventInterest::BUTTON
+            | EventInterest::CLIENT_MESSAGE
+            | EventInterest::EXPOSE
+            | EventInterest::PROPERTY_CHANGE
+    }
+
     fn draw(
         &mut self,
         connection: &XCBConnection,
@@ -465,8 +754,11 @@ impl Widget for SysTray {
         offset: f32,
     ) -> Result<(), crate::Error> {
         for (i, ti) in self.tray_icons.iter_mut().enumerate() {
-            let x = (offset + ((self.icons_size + self.padding) * i as u32) as f32) as i32;
-            let configure = ConfigureWindowAux::new().x(x);
+            let position = (offset + ((self.icons_size + self.padding) * i as u32) as f32) as i32;
+            let configure = match self.orientation {
+                TrayOrientation::Horizontal => ConfigureWindowAux::new().x(position),
+                TrayOrientation::Vertical => ConfigureWindowAux::new().y(position),
+            };
             connection.configure_window(ti.wrapper_window, &configure)?;
             if ti.should_be_mapped && !ti.has_been_mapped {
                 connection.map_window(ti.wrapper_window)?;
@@ -483,11 +775,58 @@ impl Widget for SysTray {
         Ok(())
     }
 
+    fn destroy(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        _state: &mut State,
+    ) -> Result<(), crate::Error> {
+        // hand the icons back to the root window so they survive us
+        // and the next tray can embed them
+        self.release_icons(connection, screen_num)?;
+
+        if self.active {
+            connection
+                .set_selection_owner(x11rb::NONE, self._net_system_tray_s, CURRENT_TIME)?
+                .check()?;
+            self.active = false;
+        }
+
+        Ok(())
+    }
+
+    fn on_resize(
+        &mut self,
+        connection: &XCBConnection,
+        width: u32,
+        height: u32,
+        state: &mut State,
+    ) -> Result<(), crate::Error> {
+        self.sync_owner_geometry(connection, state)?;
+
+        // the position along the bar is set again when the icons are drawn
+        let across = self.across(width, height) as i32;
+        let configure = match self.orientation {
+            TrayOrientation::Horizontal => ConfigureWindowAux::new().y(across),
+            TrayOrientation::Vertical => ConfigureWindowAux::new().x(across),
+        };
+
+        for icon in self.tray_icons.iter() {
+            connection.configure_window(icon.wrapper_window, &configure)?;
+        }
+
+        Ok(())
+    }
+
     fn size(&mut self, _state: &mut State) -> f32 {
         ((self.icons_size + self.padding) * self.tray_icons.len() as u32) as f32
     }
 
-    fn alignment(&self) -> super::Alignment {
-        super::Alignment::Right
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
     }
 }
@@ -0,0 +1,435 @@
+use crossbeam::channel::Sender;
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window},
+        Event,
+    },
+    xcb_ffi::XCBConnection,
+    CURRENT_TIME,
+};
+
+use mdry::{
+    color::Color,
+    shapes::{ClipRect, Rect, Shape},
+    window::Atoms,
+    State,
+};
+
+use super::{
+    select_events, truncate_to_width, window_title::window_title, Alignment, EventInterest, Widget,
+};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
+
+const LEFT_BTN: u8 = 1;
+const SCROLL_UP: u8 = 4;
+const SCROLL_DOWN: u8 = 5;
+
+/// the source indication for `_NET_ACTIVE_WINDOW` requests coming from pagers and taskbars
+const SOURCE_PAGER: u32 = 2;
+
+struct Entry {
+    window: Window,
+    title: String,
+    /// `title` cut down to the entry width, `None` until the next layout
+    display: Option<String>,
+    /// relative to the start of the widget
+    x: f32,
+    width: f32,
+}
+
+/// A button for every window in `_NET_CLIENT_LIST`, clicking one activates its window
+/// and scrolling over the taskbar cycles through them
+pub struct Taskbar {
+    font_size: f32,
+    color: Color,
+    selector_color: Color,
+    max_entry_width: f32,
+    padding: f32,
+    entries: Vec<Entry>,
+    active_window: Option<Window>,
+    width: f32,
+    /// where the widget was last drawn, to hit test clicks
+    offset: f32,
+    alignment: Alignment,
+}
+
+impl Taskbar {
+    pub fn new(
+        font_size: f32,
+        color: Color,
+        selector_color: Color,
+        max_entry_width: f32,
+        padding: f32,
+    ) -> Self {
+        Self {
+            font_size,
+            color,
+            selector_color,
+            max_entry_width,
+            padding,
+            entries: Vec::new(),
+            active_window: None,
+            width: 0.,
+            offset: 0.,
+            alignment: Alignment::Left,
+        }
+    }
+
+    /// `font_size`, `color`, `selector_color`, `max_entry_width` and `padding`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let font_size = config::get_f32(table, "font_size", context.bar_height as f32)?;
+
+        Ok(Self::new(
+            font_size,
+            config::get_color(table, "color", context.foreground)?,
+            config::get_color(table, "selector_color", Color::rgb(233, 86, 120))?,
+            config::get_f32(table, "max_entry_width", 200.)?,
+            config::get_f32(table, "padding", 10.)?,
+        ))
+    }
+
+    fn metrics(&self) -> glyphon::Metrics {
+        glyphon::Metrics::new(self.font_size, self.font_size)
+    }
+
+    /// rebuilds the entries from `_NET_CLIENT_LIST`, keeping the titles of known windows
+    fn update_clients(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        atoms: &Atoms,
+    ) -> Result<(), crate::Error> {
+        let root = connection.setup().roots[screen_num].root;
+
+        let reply = connection
+            .get_property(
+                false,
+                root,
+                atoms._NET_CLIENT_LIST,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+        let clients: Vec<Window> = reply.value32().into_iter().flatten().collect();
+
+        let mut old = std::mem::take(&mut self.entries);
+        for window in clients {
+            if let Some(i) = old.iter().position(|entry| entry.window == window) {
+                self.entries.push(old.swap_remove(i));
+                continue;
+            }
+
+            if skips_taskbar(connection, window, atoms)? {
+                continue;
+            }
+
+            // the window is already gone
+            let Some(title) = window_title(connection, window, atoms)? else {
+                continue;
+            };
+
+            // to hear about title changes
+            select_events(connection, window, EventMask::PROPERTY_CHANGE)?;
+
+            self.entries.push(Entry {
+                window,
+                title,
+                display: None,
+                x: 0.,
+                width: 0.,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn update_active_window(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        atoms: &Atoms,
+    ) -> Result<(), crate::Error> {
+        let root = connection.setup().roots[screen_num].root;
+
+        self.active_window = connection
+            .get_property(
+                false,
+                root,
+                atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
+            .reply()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .filter(|window| *window != x11rb::NONE);
+
+        Ok(())
+    }
+
+    /// truncates the titles that changed and places every entry after the previous one
+    fn layout(&mut self, state: &mut State) {
+        let metrics = self.metrics();
+
+        let mut x = self.padding;
+        for entry in self.entries.iter_mut() {
+            if entry.display.is_none() {
+                let display = truncate_to_width(state, &entry.title, metrics, self.max_entry_width);
+                entry.width = state.measure_text(&display, metrics).0;
+                entry.display = Some(display);
+            }
+
+            entry.x = x;
+            x += entry.width + self.padding;
+        }
+
+        self.width = x;
+    }
+
+    fn contains(&self, event_x: f32, event_y: f32, height: f32) -> bool {
+        let bounds = ClipRect {
+            x: self.offset,
+            y: 0.,
+            width: self.width,
+            height,
+        };
+        bounds.contains(event_x, event_y)
+    }
+
+    /// the window after the active one, or before it when `!forward`,
+    /// wrapping around at the ends of the list
+    fn cycle(&self, forward: bool) -> Option<Window> {
+        let len = self.entries.len();
+        if len == 0 {
+            return None;
+        }
+
+        let active = self
+            .entries
+            .iter()
+            .position(|entry| Some(entry.window) == self.active_window);
+
+        let next = match (active, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+
+        Some(self.entries[next].window)
+    }
+
+    /// asks the window manager to focus and raise `window`
+    fn activate(
+        &self,
+        connection: &XCBConnection,
+        root: Window,
+        atoms: &Atoms,
+        window: Window,
+    ) -> Result<(), crate::Error> {
+        let message = ClientMessageEvent::new(
+            32,
+            window,
+            atoms._NET_ACTIVE_WINDOW,
+            [
+                SOURCE_PAGER,
+                CURRENT_TIME,
+                self.active_window.unwrap_or(x11rb::NONE),
+                0,
+                0,
+            ],
+        );
+
+        connection
+            .send_event(
+                false,
+                root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                message,
+            )?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// the entry under the pointer, each one reaches half the padding into the gaps around it
+    fn entry_at(&self, event_x: f32, event_y: f32, height: f32) -> Option<&Entry> {
+        self.entries.iter().find(|entry| {
+            let bounds = ClipRect {
+                x: self.offset + entry.x - self.padding / 2.,
+                y: 0.,
+                width: entry.width + self.padding,
+                height,
+            };
+            bounds.contains(event_x, event_y)
+        })
+    }
+}
+
+/// whether the window asked to be left out of taskbars with `_NET_WM_STATE_SKIP_TASKBAR`
+fn skips_taskbar(
+    connection: &XCBConnection,
+    window: Window,
+    atoms: &Atoms,
+) -> Result<bool, crate::Error> {
+    let Ok(reply) = connection
+        .get_property(false, window, atoms._NET_WM_STATE, AtomEnum::ATOM, 0, 1024)?
+        .reply()
+    else {
+        return Ok(false);
+    };
+
+    let skips = reply
+        .value32()
+        .into_iter()
+        .flatten()
+        .any(|atom| atom == atoms._NET_WM_STATE_SKIP_TASKBAR);
+
+    Ok(skips)
+}
+
+impl Widget for Taskbar {
+    fn name(&self) -> &str {
+        "taskbar"
+    }
+
+    fn setup(
+        &mut self,
+        state: &mut State,
+        connection: &XCBConnection,
+        screen_num: usize,
+        _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
+    ) -> Result<(), crate::Error> {
+        let atoms = state.window().atoms;
+
+        self.update_clients(connection, screen_num, &atoms)?;
+        self.update_active_window(connection, screen_num, &atoms)
+    }
+
+    fn on_event(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        state: &mut State,
+        event: Event,
+        redraw_sender: Sender<()>,
+    ) -> Result<(), crate::Error> {
+        let atoms = state.window().atoms;
+        let root = connection.setup().roots[screen_num].root;
+
+        match event {
+            Event::PropertyNotify(event) if event.window == root => {
+                if event.atom == atoms._NET_CLIENT_LIST {
+                    self.update_clients(connection, screen_num, &atoms)?;
+                    redraw_sender.send(())?;
+                } else if event.atom == atoms._NET_ACTIVE_WINDOW {
+                    self.update_active_window(connection, screen_num, &atoms)?;
+                    redraw_sender.send(())?;
+                }
+            }
+            Event::PropertyNotify(event)
+                if event.atom == atoms._NET_WM_NAME || event.atom == atoms.WM_NAME =>
+            {
+                let Some(entry) = self
+                    .entries
+                    .iter_mut()
+                    .find(|entry| entry.window == event.window)
+                else {
+                    return Ok(());
+                };
+
+                if let Some(title) = window_title(connection, entry.window, &atoms)? {
+                    if title != entry.title {
+                        entry.title = title;
+                        entry.display = None;
+                        redraw_sender.send(())?;
+                    }
+                }
+            }
+            // presses on other windows, like tray icons or the calendar, are in their coordinates
+            Event::ButtonPress(event) if event.event != state.window().xid => {}
+            Event::ButtonPress(event) if event.detail == LEFT_BTN => {
+                if let Some(entry) = self.entry_at(
+                    event.event_x as f32,
+                    event.event_y as f32,
+                    state.height as f32,
+                ) {
+                    self.activate(connection, root, &atoms, entry.window)?;
+                }
+            }
+            Event::ButtonPress(event)
+                if matches!(event.detail, SCROLL_UP | SCROLL_DOWN)
+                    && self.contains(
+                        event.event_x as f32,
+                        event.event_y as f32,
+                        state.height as f32,
+                    ) =>
+            {
+                if let Some(window) = self.cycle(event.detail == SCROLL_DOWN) {
+                    self.activate(connection, root, &atoms, window)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::PROPERTY_CHANGE | EventInterest::BUTTON
+    }
+
+    fn draw(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        state: &mut State,
+        offset: f32,
+    ) -> Result<(), crate::Error> {
+        self.layout(state);
+        self.offset = offset;
+
+        for entry in self.entries.iter() {
+            let Some(display) = entry.display.as_deref() else {
+                continue;
+            };
+
+            if !display.is_empty() {
+                state.draw_text_absolute_cached(display, entry.x, 0., self.color, self.font_size);
+            }
+
+            if Some(entry.window) == self.active_window {
+                let rect = Rect {
+                    x: entry.x,
+                    y: state.height as f32 - 2.,
+                    width: entry.width as u32,
+                    height: 2,
+                    color: self.selector_color,
+                };
+
+                // same as the pager, over anything else drawn in the taskbar
+                state.draw_shape_absolute_with_z(Shape::Rect(rect), 1.);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn size(&mut self, state: &mut State) -> f32 {
+        self.layout(state);
+        self.width
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+}
@@ -1,7 +1,12 @@
 use crossbeam::channel::Sender;
-use mdry::{color::Color, State};
+use mdry::{
+    color::Color,
+    shapes::{Rect, Shape},
+    State,
+};
 
-use super::Widget;
+use super::{EventInterest, Widget};
+use crate::scheduler::Scheduler;
 
 pub struct TextWidget {
     content: String,
@@ -47,18 +52,47 @@ impl TextWidget {
         self.y
     }
 
+    /// fills the text's `width` and `height` behind it, `None` to draw only the text
+    pub fn set_background(&mut self, background: Option<Color>) {
+        if self.background != background {
+            self.background = background;
+            self.requires_redraw = true;
+        }
+    }
+
     pub fn set_redraw(&mut self, redraw: bool) {
         self.requires_redraw = redraw;
     }
+
+    /// draws the text, and the background if there is one
+    pub fn paint(&self, state: &mut State) {
+        if let Some(background) = self.background {
+            // shapes are rendered before texts, so this stays behind the text
+            state.draw_shape_absolute(Shape::Rect(Rect {
+                x: self.x,
+                y: self.y,
+                width: self.width.ceil() as u32,
+                height: self.height.ceil() as u32,
+                color: background,
+            }));
+        }
+
+        state.draw_text_absolute_cached(&self.content, self.x, self.y, self.color, self.font_size);
+    }
 }
 
 impl Widget for TextWidget {
+    fn name(&self) -> &str {
+        "text"
+    }
+
     fn setup(
         &mut self,
         _state: &mut State,
         _connection: &x11rb::xcb_ffi::XCBConnection,
         _screen_num: usize,
         _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
     ) -> Result<(), crate::Error> {
         Ok(())
     }
@@ -80,20 +114,18 @@ impl Widget for TextWidget {
         Ok(())
     }
 
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::EXPOSE
+    }
+
     fn draw(
         &mut self,
         _connection: &x11rb::xcb_ffi::XCBConnection,
         _screen_num: usize,
         state: &mut State,
-        offset: f32,
+        _offset: f32,
     ) -> Result<(), crate::Error> {
-        state.draw_text_absolute_cached(
-            &self.content,
-            self.x + offset,
-            self.y,
-            self.color,
-            self.font_size,
-        );
+        self.paint(state);
 
         Ok(())
     }
@@ -105,4 +137,8 @@ impl Widget for TextWidget {
     fn requires_redraw(&self) -> bool {
         self.requires_redraw
     }
+
+    fn clear_redraw(&mut self) {
+        self.requires_redraw = false;
+    }
 }
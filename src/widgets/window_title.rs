@@ -0,0 +1,236 @@
+use crossbeam::channel::Sender;
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{AtomEnum, ConnectionExt, EventMask, Window},
+        Event,
+    },
+    xcb_ffi::XCBConnection,
+};
+
+use mdry::{color::Color, window::Atoms, State};
+
+use super::{select_events, truncate_to_width, Alignment, EventInterest, Widget};
+use crate::config::{self, WidgetContext};
+use crate::scheduler::Scheduler;
+
+/// The title of the focused window
+pub struct WindowTitle {
+    font_size: f32,
+    color: Color,
+    max_width: f32,
+    active_window: Option<Window>,
+    title: String,
+    /// `title` cut down to `max_width`, measured again when the title changes
+    display: Option<String>,
+    alignment: Alignment,
+}
+
+impl WindowTitle {
+    pub fn new(font_size: f32, color: Color, max_width: f32) -> Self {
+        Self {
+            font_size,
+            color,
+            max_width,
+            active_window: None,
+            title: String::new(),
+            display: None,
+            alignment: Alignment::Left,
+        }
+    }
+
+    /// `font_size`, `color` and `max_width`
+    pub fn from_config(table: &toml::Table, context: &WidgetContext) -> Result<Self, crate::Error> {
+        let font_size = config::get_f32(table, "font_size", context.bar_height as f32)?;
+
+        Ok(Self::new(
+            font_size,
+            config::get_color(table, "color", context.foreground)?,
+            config::get_f32(table, "max_width", 400.)?,
+        ))
+    }
+
+    fn metrics(&self) -> glyphon::Metrics {
+        glyphon::Metrics::new(self.font_size, self.font_size)
+    }
+
+    /// follows `_NET_ACTIVE_WINDOW` to the focused window and reads its title
+    fn update_active_window(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        atoms: &Atoms,
+    ) -> Result<(), crate::Error> {
+        let root = connection.setup().roots[screen_num].root;
+
+        let active_window = connection
+            .get_property(
+                false,
+                root,
+                atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
+            .reply()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .filter(|window| *window != x11rb::NONE);
+
+        if active_window != self.active_window {
+            // to hear about title changes, the mask on the previous window is left alone
+            // since other widgets on the same connection may be listening to it too
+            if let Some(new) = active_window {
+                select_events(connection, new, EventMask::PROPERTY_CHANGE)?;
+            }
+
+            self.active_window = active_window;
+        }
+
+        self.update_title(connection, atoms)
+    }
+
+    fn update_title(
+        &mut self,
+        connection: &XCBConnection,
+        atoms: &Atoms,
+    ) -> Result<(), crate::Error> {
+        let title = match self.active_window {
+            Some(window) => window_title(connection, window, atoms)?.unwrap_or_default(),
+            None => String::new(),
+        };
+
+        if title != self.title {
+            self.title = title;
+            self.display = None;
+        }
+
+        Ok(())
+    }
+
+    fn display(&mut self, state: &mut State) -> &str {
+        if self.display.is_none() {
+            let metrics = self.metrics();
+            self.display = Some(truncate_to_width(
+                state,
+                &self.title,
+                metrics,
+                self.max_width,
+            ));
+        }
+
+        self.display.as_deref().unwrap_or_default()
+    }
+}
+
+/// `_NET_WM_NAME`, or the legacy `WM_NAME` for windows that don't set it,
+/// `None` if the window is gone
+pub fn window_title(
+    connection: &XCBConnection,
+    window: Window,
+    atoms: &Atoms,
+) -> Result<Option<String>, crate::Error> {
+    for (atom, type_) in [
+        (atoms._NET_WM_NAME, atoms.UTF8_STRING),
+        (atoms.WM_NAME, AtomEnum::ANY.into()),
+    ] {
+        let Ok(reply) = connection
+            .get_property(false, window, atom, type_, 0, u32::MAX)?
+            .reply()
+        else {
+            return Ok(None);
+        };
+
+        if !reply.value.is_empty() {
+            return Ok(Some(String::from_utf8_lossy(&reply.value).into_owned()));
+        }
+    }
+
+    Ok(Some(String::new()))
+}
+
+impl Widget for WindowTitle {
+    fn name(&self) -> &str {
+        "title"
+    }
+
+    fn setup(
+        &mut self,
+        state: &mut State,
+        connection: &XCBConnection,
+        screen_num: usize,
+        _redraw_sender: Sender<()>,
+        _scheduler: &Scheduler,
+    ) -> Result<(), crate::Error> {
+        let atoms = state.window().atoms;
+        self.update_active_window(connection, screen_num, &atoms)
+    }
+
+    fn on_event(
+        &mut self,
+        connection: &XCBConnection,
+        screen_num: usize,
+        state: &mut State,
+        event: Event,
+        redraw_sender: Sender<()>,
+    ) -> Result<(), crate::Error> {
+        let Event::PropertyNotify(event) = event else {
+            return Ok(());
+        };
+
+        let atoms = state.window().atoms;
+        let root = connection.setup().roots[screen_num].root;
+
+        if event.window == root && event.atom == atoms._NET_ACTIVE_WINDOW {
+            self.update_active_window(connection, screen_num, &atoms)?;
+            redraw_sender.send(())?;
+        } else if Some(event.window) == self.active_window
+            && (event.atom == atoms._NET_WM_NAME || event.atom == atoms.WM_NAME)
+        {
+            self.update_title(connection, &atoms)?;
+            redraw_sender.send(())?;
+        }
+
+        Ok(())
+    }
+
+    fn event_mask(&self) -> EventInterest {
+        EventInterest::PROPERTY_CHANGE
+    }
+
+    fn draw(
+        &mut self,
+        _connection: &XCBConnection,
+        _screen_num: usize,
+        state: &mut State,
+        _offset: f32,
+    ) -> Result<(), crate::Error> {
+        let (font_size, color) = (self.font_size, self.color);
+        let display = self.display(state).to_string();
+
+        if !display.is_empty() {
+            state.draw_text_absolute_cached(&display, 0., 0., color, font_size);
+        }
+
+        Ok(())
+    }
+
+    fn size(&mut self, state: &mut State) -> f32 {
+        let metrics = self.metrics();
+        let display = self.display(state).to_string();
+
+        if display.is_empty() {
+            return 0.;
+        }
+
+        state.measure_text(&display, metrics).0
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+}